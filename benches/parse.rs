@@ -0,0 +1,57 @@
+//! Times the reader over inputs that double in size, so the time per byte column should stay
+//! roughly flat if parsing is linear.
+//!
+//! Run with `cargo bench --bench parse`.
+
+extern crate rusptlib;
+
+use std::time::{Duration, Instant};
+
+use rusptlib::parse;
+
+const RUNS: u32 = 5;
+
+fn main() {
+    bench("flat data", |n| {
+        let row = "(\"row\" 1 2.5 foo (nested list)) ; comment\n";
+        format!("(data\n{})", row.repeat(n))
+    });
+
+    bench("deep nesting", |n| format!("{}1{}", "('(".repeat(n), "))".repeat(n)));
+}
+
+fn bench<F>(name: &str, make_input: F)
+where
+    F: Fn(usize) -> String,
+{
+    println!("{}", name);
+    println!("{:>12} {:>14} {:>12} {:>12}", "n", "bytes", "ms", "ns/byte");
+
+    let mut n = 12_500;
+    while n <= 400_000 {
+        let input = make_input(n);
+        let bytes = input.len();
+
+        let mut best = Duration::from_secs(u64::MAX);
+        for _ in 0..RUNS {
+            let input = input.clone();
+
+            let start = Instant::now();
+            let program = parse(input).expect("bench input should parse");
+            let elapsed = start.elapsed();
+
+            drop(program);
+
+            if elapsed < best {
+                best = elapsed;
+            }
+        }
+
+        let nanos = best.as_secs() as f64 * 1e9 + best.subsec_nanos() as f64;
+        println!("{:>12} {:>14} {:>12.2} {:>12.2}", n, bytes, nanos / 1e6, nanos / bytes as f64);
+
+        n *= 2;
+    }
+
+    println!();
+}
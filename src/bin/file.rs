@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process;
+use std::rc::Rc;
+
+use rusptlib::{exec_prog, print_cell, read_forms, Environment};
+
+/// Runs each form in the file at `path` (or stdin for `-`) as soon as it's been read.
+pub fn run_file(path: String) {
+    let input: Box<dyn BufRead> = match path.as_str() {
+        "-" => Box::new(BufReader::new(io::stdin())),
+        _ => match File::open(&path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(err) => {
+                eprintln!("Unable to open {}: {}", &path, err);
+                process::exit(1);
+            }
+        },
+    };
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut forms = read_forms(input);
+    let mut last_result = None;
+
+    loop {
+        let program = match forms.next() {
+            Some(Ok(program)) => program,
+            Some(Err(err)) => {
+                eprintln!("{}", forms.reader().render_error(&err));
+                process::exit(1);
+            }
+            None => break,
+        };
+
+        let text = program.text.clone();
+        match exec_prog(env.clone(), program) {
+            Ok(result) => last_result = Some(result),
+            Err(err) => {
+                eprintln!("{}", err.render(&text));
+                process::exit(1);
+            }
+        }
+    }
+
+    if let Some(result) = last_result {
+        println!("{}", print_cell(result));
+    }
+}
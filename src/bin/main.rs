@@ -1,6 +1,5 @@
 #[macro_use]
 extern crate serde_derive;
-#[macro_use]
 extern crate json;
 
 extern crate actix;
@@ -12,17 +11,21 @@ extern crate serde_json;
 
 extern crate rusptlib;
 
+mod file;
 mod repl;
 mod server;
 
+use file::*;
 use repl::*;
 use server::*;
 
 use std::env;
+use std::time::Duration;
 
 enum RunMode {
     Repl,
     Server,
+    File(String),
 }
 
 fn main() {
@@ -31,6 +34,7 @@ fn main() {
     let mut run_mode = None;
 
     let mut server_addr = None;
+    let mut server_config = ServerConfig::default();
 
     loop {
         let arg = args.next();
@@ -38,12 +42,22 @@ fn main() {
         match arg {
             Some(arg) => match arg.as_str() {
                 "--repl" => run_mode = Some(RunMode::Repl),
+                "--file" => match args.next() {
+                    Some(path) => run_mode = Some(RunMode::File(path)),
+                    None => panic!("--file requires a path (or - for stdin)"),
+                },
 
                 "--server" => run_mode = Some(RunMode::Server),
                 "--addr" => match args.next() {
                     Some(addr) => server_addr = Some(addr),
                     None => panic!("--addr requires an address"),
                 },
+                "--max-stack-depth" => server_config.exec_options.max_stack_depth = parse_limit(&arg, args.next()),
+                "--max-steps" => server_config.exec_options.max_steps = Some(parse_limit(&arg, args.next())),
+                "--max-cells" => server_config.exec_options.max_cells = Some(parse_limit(&arg, args.next())),
+                "--timeout-ms" => {
+                    server_config.exec_options.timeout = Some(Duration::from_millis(parse_limit(&arg, args.next())))
+                }
 
                 _ => panic!("Unknown option {:?}", arg),
             },
@@ -53,13 +67,21 @@ fn main() {
 
     match run_mode {
         None | Some(RunMode::Repl) => repl(),
+        Some(RunMode::File(path)) => run_file(path),
         Some(RunMode::Server) => {
             let addr = match server_addr {
                 None => String::from("127.0.0.1:8081"),
                 Some(addr) => addr,
             };
 
-            server(addr)
+            server(addr, server_config)
         }
     }
 }
+
+fn parse_limit<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    match value.map(|value| value.parse()) {
+        Some(Ok(limit)) => limit,
+        _ => panic!("{} requires a number", flag),
+    }
+}
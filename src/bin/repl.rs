@@ -1,25 +1,74 @@
-use std::env;
-use std::rc::Rc;
 use std::cell::RefCell;
 use std::io::{self, Write};
+use std::rc::Rc;
 
-use rusptlib::{exec_prog, parse, Environment};
+use rusptlib::{exec_prog, Environment, LispCell, Reader};
 
 pub fn repl() {
     println!("Welcome to ruspt!");
 
-    let mut env = Rc::new(RefCell::new(Environment::new()));
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut reader = Reader::new();
 
     loop {
-        print!("> ");
+        // Keep prompting for more lines until the form being typed in is finished
+        if reader.is_mid_form() {
+            print!(". ");
+        } else {
+            print!("> ");
+        }
         io::stdout().flush().unwrap();
 
         let mut buffer = String::new();
-        io::stdin().read_line(&mut buffer).unwrap();
+        let read = io::stdin().read_line(&mut buffer).unwrap();
+
+        let line = buffer.trim();
+        if !reader.is_mid_form() && line.starts_with(":doc ") {
+            show_doc(&env.borrow(), line[":doc ".len()..].trim());
+            continue;
+        }
+
+        match read {
+            0 => reader.finish(),
+            _ => reader.feed(&buffer),
+        }
 
-        let program = parse(buffer);
-        let result = exec_prog(env.clone(), program);
+        loop {
+            match reader.next_form() {
+                Ok(Some(program)) => {
+                    let text = program.text.clone();
 
-        println!("{:?}", result);
+                    match exec_prog(env.clone(), program) {
+                        Ok(result) => println!("{:?}", result),
+                        Err(err) => println!("{}", err.render(&text)),
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    println!("{}", reader.render_error(&err));
+                    break;
+                }
+            }
+        }
+
+        if reader.is_finished() {
+            break;
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Prints the docstring of the function bound to `name`, for the `:doc name` command.
+fn show_doc(env: &Environment, name: &str) {
+    let func = match env.find_sym(&name.to_string()) {
+        Some(func) => func,
+        None => return println!("unbound symbol: {}", name),
+    };
+
+    match *func.borrow() {
+        LispCell::Func(ref func) => match func.doc {
+            Some(ref doc) => println!("{}", doc),
+            None => println!("no documentation for {}", name),
+        },
+        _ => println!("{} is not a function", name),
+    };
+}
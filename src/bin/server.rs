@@ -1,68 +1,129 @@
-use std::panic;
+use std::any::Any;
 use std::cell::RefCell;
+use std::panic;
 use std::rc::Rc;
+use std::time::Duration;
 
 use actix_web::{http, middleware, server, App, AsyncResponder, Error, HttpMessage, HttpRequest, HttpResponse};
 use futures::Future;
 
 use rusptlib;
 
+/// Limits applied to all submitted code. Each request may lower them but never raise them.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub exec_options: rusptlib::ExecOptions,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            exec_options: rusptlib::ExecOptions {
+                max_stack_depth: 100_000,
+                max_steps: Some(10_000_000),
+                max_cells: Some(10_000_000),
+                timeout: Some(Duration::from_secs(5)),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct SubmitCodeRequest {
     pub code: String,
+    pub max_stack_depth: Option<usize>,
+    pub max_steps: Option<u64>,
+    pub max_cells: Option<usize>,
+    pub timeout_ms: Option<u64>,
+}
+
+impl SubmitCodeRequest {
+    /// Tightens `limits` with any lower limits requested.
+    fn limit(&self, limits: &rusptlib::ExecOptions) -> rusptlib::ExecOptions {
+        let timeout = self.timeout_ms.map(Duration::from_millis);
+
+        rusptlib::ExecOptions {
+            max_stack_depth: self.max_stack_depth.map_or(limits.max_stack_depth, |max| max.min(limits.max_stack_depth)),
+            max_steps: lower_limit(limits.max_steps, self.max_steps),
+            max_cells: lower_limit(limits.max_cells, self.max_cells),
+            timeout: lower_limit(limits.timeout, timeout),
+        }
+    }
+}
+
+fn lower_limit<T: Ord>(limit: Option<T>, requested: Option<T>) -> Option<T> {
+    match (limit, requested) {
+        (Some(limit), Some(requested)) => Some(limit.min(requested)),
+        (limit, None) => limit,
+        (None, requested) => requested,
+    }
 }
 
 #[derive(Debug, Serialize)]
 struct SubmitCodeResponse {
     pub output: String,
     pub success: bool,
+    pub line: Option<usize>,
+    pub col: Option<usize>,
 }
 
-fn run_code(code: String) -> SubmitCodeResponse {
+fn run_code(code: String, options: &rusptlib::ExecOptions) -> SubmitCodeResponse {
+    // Evaluation reports what goes wrong as a `LispError`, but a panic from a bug in the interpreter
+    // still shouldn't take the worker thread down with it
     let exec_result = panic::catch_unwind(|| {
-        let mut env = Rc::new(RefCell::new(rusptlib::Environment::new()));
-        let program = rusptlib::parse(code);
+        let env = Rc::new(RefCell::new(rusptlib::Environment::new()));
 
-        let result = rusptlib::exec_prog(env, program);
-
-        rusptlib::print_cell(result)
+        rusptlib::parse_and_exec_with_options(env, code.clone(), options).map(rusptlib::print_cell)
     });
 
     match exec_result {
-        Ok(res) => SubmitCodeResponse {
-            output: res,
+        Ok(Ok(output)) => SubmitCodeResponse {
+            output,
             success: true,
+            line: None,
+            col: None,
+        },
+        Ok(Err(err)) => SubmitCodeResponse {
+            output: err.render(&code),
+            success: false,
+            line: err.span.map(|span| span.line),
+            col: err.span.map(|span| span.col),
+        },
+        Err(payload) => SubmitCodeResponse {
+            output: panic_message(payload),
+            success: false,
+            line: None,
+            col: None,
         },
-        Err(msg) => {
-            let msg = {
-                if let Some(msg) = msg.downcast_ref::<&'static str>() {
-                    format!("{:?}", msg)
-                } else if let Some(msg) = msg.downcast_ref::<String>() {
-                    format!("{:?}", msg)
-                } else {
-                    format!("{:?}", msg)
-                }
-            };
-
-            SubmitCodeResponse {
-                output: msg,
-                success: false,
-            }
-        }
     }
 }
 
-fn submit_code_handler(req: &HttpRequest) -> Box<Future<Item = HttpResponse, Error = Error>> {
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let msg = match payload.downcast_ref::<&'static str>() {
+        Some(msg) => msg.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(msg) => msg.clone(),
+            None => "unknown error".to_string(),
+        },
+    };
+
+    format!("internal error: {}", msg)
+}
+
+fn submit_code_handler(req: &HttpRequest<ServerConfig>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    let limits = req.state().exec_options.clone();
+
     req.json()
         .from_err()
-        .and_then(|submit_code_req: SubmitCodeRequest| {
-            let response = run_code(submit_code_req.code);
+        .and_then(move |submit_code_req: SubmitCodeRequest| {
+            let options = submit_code_req.limit(&limits);
+            let response = run_code(submit_code_req.code, &options);
 
             Ok(HttpResponse::Ok().json(response))
         }).responder()
 }
 
-pub fn server(addr: String) {
+pub fn server(addr: String, config: ServerConfig) {
     println!("Starting server...");
 
     ::std::env::set_var("RUST_LOG", "actix_web=info");
@@ -70,8 +131,8 @@ pub fn server(addr: String) {
 
     let actix_sys = actix::System::new("ruspt-server");
 
-    server::new(|| {
-        App::new()
+    server::new(move || {
+        App::with_state(config.clone())
             .middleware(middleware::Logger::default())
             .middleware(middleware::cors::Cors::default())
             .resource("/submit-code", |r| r.method(http::Method::POST).f(submit_code_handler))
@@ -0,0 +1,443 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// An arbitrary precision integer, stored as a sign and a little-endian base 2^32 magnitude with no
+/// trailing zero limbs. Zero has an empty magnitude and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt {
+            negative: false,
+            magnitude: vec![],
+        }
+    }
+
+    pub fn from_i64(num: i64) -> BigInt {
+        // `wrapping_abs` leaves i64::MIN as is, which is still 2^63 once it's reinterpreted as unsigned
+        let abs = num.wrapping_abs() as u64;
+
+        BigInt::from_parts(num < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    /// Parses an optionally signed string of digits in the given radix.
+    pub fn parse(text: &str, radix: u32) -> Option<BigInt> {
+        let (negative, digits) = match text.chars().next() {
+            Some('-') => (true, &text[1..]),
+            Some('+') => (false, &text[1..]),
+            _ => (false, text),
+        };
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return None;
+        }
+
+        // Each pass over the magnitude adds as many digits as fit in a `u32` at once rather than
+        // just the one
+        let (mut chunk_len, mut chunk_factor) = (1, radix);
+        while let Some(factor) = chunk_factor.checked_mul(radix) {
+            chunk_len += 1;
+            chunk_factor = factor;
+        }
+
+        let mut magnitude = vec![];
+        for chunk in digits.as_bytes().chunks(chunk_len) {
+            let value = chunk.iter().fold(0, |acc, &digit| {
+                acc * radix + (digit as char).to_digit(radix).expect("digits were checked already")
+            });
+
+            mul_add_small(&mut magnitude, radix.pow(chunk.len() as u32), value);
+        }
+
+        Some(BigInt::from_parts(negative, magnitude))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_one(&self) -> bool {
+        !self.negative && self.magnitude == [1]
+    }
+
+    /// The number of bits in the magnitude.
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(top) => self.magnitude.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::from_parts(false, self.magnitude.clone())
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+
+        let abs = self.magnitude.iter().rev().fold(0u64, |acc, &limb| (acc << 32) | limb as u64);
+
+        match self.negative {
+            true if abs <= 1 << 63 => Some((abs as i64).wrapping_neg()),
+            false if abs <= i64::MAX as u64 => Some(abs as i64),
+            _ => None,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let abs = self.magnitude.iter().rev().fold(0f64, |acc, &limb| acc * 4294967296f64 + limb as f64);
+
+        match self.negative {
+            true => -abs,
+            false => abs,
+        }
+    }
+
+    /// Truncating division, so the remainder takes the sign of `self`. Returns `None` when dividing
+    /// by zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &other.magnitude);
+
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    /// The greatest common divisor of `self` and `other`, which is never negative.
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut a = self.abs();
+        let mut b = other.abs();
+
+        while !b.is_zero() {
+            let (_, remainder) = a.div_rem(&b).expect("b is non-zero");
+
+            a = b;
+            b = remainder;
+        }
+
+        a
+    }
+}
+
+impl<'a> Add<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.magnitude, &other.magnitude));
+        }
+
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_magnitude(&other.magnitude, &self.magnitude)),
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.magnitude, &other.magnitude)),
+        }
+    }
+}
+
+impl<'a> Sub<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl<'a> Mul<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, mul_magnitude(&self.magnitude, &other.magnitude))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off 9 decimal digits at a time, least significant first
+        let mut chunks = vec![];
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_small(&magnitude, 1_000_000_000);
+
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn trimmed(mut magnitude: Vec<u32>) -> Vec<u32> {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+
+    magnitude
+}
+
+fn mul_add_small(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+
+    for limb in magnitude.iter_mut() {
+        let product = *limb as u64 * factor as u64 + carry;
+
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = match a.len() >= b.len() {
+        true => (a, b),
+        false => (b, a),
+    };
+
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+
+    for (i, &digit) in long.iter().enumerate() {
+        let sum = digit as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+
+    result
+}
+
+/// Subtracts `b` from `a`, which must be at least as large.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, &digit) in a.iter().enumerate() {
+        let mut diff = digit as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+
+        borrow = match diff < 0 {
+            true => {
+                diff += 1 << 32;
+                1
+            }
+            false => 0,
+        };
+
+        result.push(diff as u32);
+    }
+
+    trimmed(result)
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+
+        for (j, &y) in b.iter().enumerate() {
+            let product = x as u64 * y as u64 + result[i + j] as u64 + carry;
+
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+
+        result[i + b.len()] = carry as u32;
+    }
+
+    trimmed(result)
+}
+
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+
+    for i in (0..a.len()).rev() {
+        let current = (remainder << 32) | a[i] as u64;
+
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+
+    (trimmed(quotient), remainder as u32)
+}
+
+fn shl_bits(a: &[u32], shift: u32, extra_limb: bool) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u32;
+
+    for &limb in a.iter() {
+        match shift {
+            0 => result.push(limb),
+            _ => {
+                result.push((limb << shift) | carry);
+                carry = limb >> (32 - shift);
+            }
+        }
+    }
+
+    if extra_limb {
+        result.push(carry);
+    }
+
+    result
+}
+
+/// Long division of magnitudes (Knuth's algorithm D), returning the quotient and remainder.
+fn div_rem_magnitude(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(u, v) == Ordering::Less {
+        return (vec![], u.to_vec());
+    }
+
+    if v.len() == 1 {
+        let (quotient, remainder) = div_rem_small(u, v[0]);
+
+        return (quotient, trimmed(vec![remainder]));
+    }
+
+    // Normalize so the divisor's top limb has its high bit set, which keeps each quotient digit
+    // estimate within 2 of the real one
+    let shift = v[v.len() - 1].leading_zeros();
+    let vn = shl_bits(v, shift, false);
+    let mut un = shl_bits(u, shift, true);
+
+    let n = vn.len();
+    let m = u.len() - n;
+    let base = 1u64 << 32;
+
+    let mut quotient = vec![0u32; m + 1];
+
+    for j in (0..m + 1).rev() {
+        let numerator = ((un[j + n] as u64) << 32) | un[j + n - 1] as u64;
+        let mut qhat = numerator / vn[n - 1] as u64;
+        let mut rhat = numerator % vn[n - 1] as u64;
+
+        while qhat >= base || qhat * vn[n - 2] as u64 > ((rhat << 32) | un[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += vn[n - 1] as u64;
+
+            if rhat >= base {
+                break;
+            }
+        }
+
+        // Multiply and subtract qhat * vn from the current window of un
+        let mut borrow = 0i64;
+        for i in 0..n {
+            let product = qhat * vn[i] as u64;
+            let diff = un[i + j] as i64 - borrow - (product & 0xFFFF_FFFF) as i64;
+
+            un[i + j] = diff as u32;
+            borrow = (product >> 32) as i64 - (diff >> 32);
+        }
+
+        let diff = un[j + n] as i64 - borrow;
+        un[j + n] = diff as u32;
+
+        // The estimate was one too big, so add a divisor back
+        if diff < 0 {
+            qhat -= 1;
+
+            let mut carry = 0u64;
+            for i in 0..n {
+                let sum = un[i + j] as u64 + vn[i] as u64 + carry;
+
+                un[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+
+            un[j + n] = un[j + n].wrapping_add(carry as u32);
+        }
+
+        quotient[j] = qhat as u32;
+    }
+
+    // Undo the normalization on what's left over
+    let mut remainder = vec![0u32; n];
+    for i in 0..n {
+        remainder[i] = match shift {
+            0 => un[i],
+            _ => (un[i] >> shift) | (un[i + 1] << (32 - shift)),
+        };
+    }
+
+    (trimmed(quotient), trimmed(remainder))
+}
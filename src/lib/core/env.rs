@@ -9,19 +9,75 @@ pub struct Environment {
     pub symbols: HashMap<String, LispCellRef>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
-    pub fn def<'a>(&mut self, symbol: String, cell: Rc<RefCell<LispCell>>) {
+    pub fn def(&mut self, symbol: String, cell: Rc<RefCell<LispCell>>) {
         log(|| println!("symbols: {:?}", &self.symbols));
 
         self.symbols.insert(symbol, cell);
     }
 
+    /// Looks `name` up in this environment and then in each of its parents in turn.
     pub fn find_sym(&self, name: &String) -> Option<Rc<RefCell<LispCell>>> {
         log(|| println!("looking up symbol {}", name));
 
-        match self.symbols.get(name) {
-            Some(sym) => Some(sym.clone()),
-            None => None,
+        if let Some(sym) = self.symbols.get(name) {
+            return Some(sym.clone());
+        }
+
+        let mut parent = self.parent.clone();
+        while let Some(env) = parent {
+            let env = env.borrow();
+
+            if let Some(sym) = env.symbols.get(name) {
+                return Some(sym.clone());
+            }
+
+            parent = env.parent.clone();
+        }
+
+        None
+    }
+
+    /// Rebinds `name` in the nearest of this environment and its parents that already binds it, and
+    /// returns whether there was one.
+    pub fn set_sym(&mut self, name: &String, cell: Rc<RefCell<LispCell>>) -> bool {
+        if let Some(sym) = self.symbols.get_mut(name) {
+            *sym = cell;
+            return true;
+        }
+
+        let mut parent = self.parent.clone();
+        while let Some(env) = parent {
+            let mut env = env.borrow_mut();
+
+            if let Some(sym) = env.symbols.get_mut(name) {
+                *sym = cell;
+                return true;
+            }
+
+            parent = env.parent.clone();
+        }
+
+        false
+    }
+
+    /// The top-level environment that `env` descends from, where `def` puts its definitions.
+    pub fn root(env: Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        let mut current = env;
+
+        loop {
+            let parent = current.borrow().parent.clone();
+
+            match parent {
+                Some(parent) => current = parent,
+                None => return current,
+            }
         }
     }
 
@@ -32,10 +88,11 @@ impl Environment {
         }
     }
 
+    /// Makes an empty environment whose lookups fall back to `env`.
     pub fn new_child(env: Rc<RefCell<Environment>>) -> Environment {
         Environment {
-            parent: Some(env.clone()),
-            symbols: env.borrow().symbols.clone(),
+            parent: Some(env),
+            symbols: HashMap::new(),
         }
     }
 
@@ -46,16 +103,78 @@ impl Environment {
         Self::add_op("-", LispFuncType::Normal, Rc::new(ops::sub), &mut map);
         Self::add_op("*", LispFuncType::Normal, Rc::new(ops::mul), &mut map);
         Self::add_op("/", LispFuncType::Normal, Rc::new(ops::div), &mut map);
+        Self::add_op("=", LispFuncType::Normal, Rc::new(ops::num_eq), &mut map);
+        Self::add_op("<", LispFuncType::Normal, Rc::new(ops::lt), &mut map);
+        Self::add_op(">", LispFuncType::Normal, Rc::new(ops::gt), &mut map);
+        Self::add_op("<=", LispFuncType::Normal, Rc::new(ops::lte), &mut map);
+        Self::add_op(">=", LispFuncType::Normal, Rc::new(ops::gte), &mut map);
+        Self::add_op("quot", LispFuncType::Normal, Rc::new(ops::quot), &mut map);
+        Self::add_op("rem", LispFuncType::Normal, Rc::new(ops::rem), &mut map);
+        Self::add_op("mod", LispFuncType::Normal, Rc::new(ops::modulo), &mut map);
+        Self::add_op("abs", LispFuncType::Normal, Rc::new(ops::abs), &mut map);
+        Self::add_op("min", LispFuncType::Normal, Rc::new(ops::min), &mut map);
+        Self::add_op("max", LispFuncType::Normal, Rc::new(ops::max), &mut map);
+        Self::add_op("floor", LispFuncType::Normal, Rc::new(ops::floor), &mut map);
+        Self::add_op("ceil", LispFuncType::Normal, Rc::new(ops::ceil), &mut map);
+        Self::add_op("round", LispFuncType::Normal, Rc::new(ops::round), &mut map);
+        Self::add_op("sqrt", LispFuncType::Normal, Rc::new(ops::sqrt), &mut map);
+        Self::add_op("expt", LispFuncType::Normal, Rc::new(ops::expt), &mut map);
+        Self::add_op("exp", LispFuncType::Normal, Rc::new(ops::exp), &mut map);
+        Self::add_op("log", LispFuncType::Normal, Rc::new(ops::logarithm), &mut map);
+        Self::add_op("sin", LispFuncType::Normal, Rc::new(ops::sin), &mut map);
+        Self::add_op("cos", LispFuncType::Normal, Rc::new(ops::cos), &mut map);
+        Self::add_op("tan", LispFuncType::Normal, Rc::new(ops::tan), &mut map);
+        Self::add_op("asin", LispFuncType::Normal, Rc::new(ops::asin), &mut map);
+        Self::add_op("acos", LispFuncType::Normal, Rc::new(ops::acos), &mut map);
+        Self::add_op("atan", LispFuncType::Normal, Rc::new(ops::atan), &mut map);
+        Self::add_op("number?", LispFuncType::Normal, Rc::new(ops::is_number), &mut map);
+        Self::add_op("integer?", LispFuncType::Normal, Rc::new(ops::is_integer), &mut map);
+        Self::add_op("rational?", LispFuncType::Normal, Rc::new(ops::is_rational), &mut map);
+        Self::add_op("float?", LispFuncType::Normal, Rc::new(ops::is_float), &mut map);
         Self::add_op("list", LispFuncType::Normal, Rc::new(ops::list), &mut map);
-        Self::add_op("def", LispFuncType::SpecialForm, Rc::new(ops::def), &mut map);
+        Self::add_step_op("def", LispFuncType::SpecialForm, Rc::new(ops::def), &mut map);
+        Self::add_step_op("defonce", LispFuncType::SpecialForm, Rc::new(ops::defonce), &mut map);
+        Self::add_step_op("set!", LispFuncType::SpecialForm, Rc::new(ops::set), &mut map);
         Self::add_op("defn", LispFuncType::SpecialForm, Rc::new(ops::defn), &mut map);
-        Self::add_op("do", LispFuncType::SpecialForm, Rc::new(ops::dew), &mut map);
+        Self::add_op("defmacro", LispFuncType::SpecialForm, Rc::new(ops::defmacro), &mut map);
+        Self::add_step_op("macroexpand", LispFuncType::Normal, Rc::new(ops::macroexpand_op), &mut map);
+        Self::add_step_op("macroexpand-1", LispFuncType::Normal, Rc::new(ops::macroexpand_1_op), &mut map);
+        Self::add_op("gensym", LispFuncType::Normal, Rc::new(ops::gensym), &mut map);
+        Self::add_step_op("do", LispFuncType::SpecialForm, Rc::new(ops::dew), &mut map);
         Self::add_op("push", LispFuncType::Normal, Rc::new(ops::push), &mut map);
         Self::add_op("car", LispFuncType::Normal, Rc::new(ops::car), &mut map);
         Self::add_op("cdr", LispFuncType::Normal, Rc::new(ops::cdr), &mut map);
-        Self::add_op("if", LispFuncType::SpecialForm, Rc::new(ops::iff), &mut map);
+        Self::add_step_op("if", LispFuncType::SpecialForm, Rc::new(ops::iff), &mut map);
         Self::add_op("eq", LispFuncType::Normal, Rc::new(ops::eq), &mut map);
+        Self::add_step_op("and", LispFuncType::SpecialForm, Rc::new(ops::and), &mut map);
+        Self::add_step_op("or", LispFuncType::SpecialForm, Rc::new(ops::or), &mut map);
+        Self::add_op("not", LispFuncType::Normal, Rc::new(ops::not), &mut map);
+        Self::add_step_op("when", LispFuncType::SpecialForm, Rc::new(ops::when), &mut map);
+        Self::add_step_op("unless", LispFuncType::SpecialForm, Rc::new(ops::unless), &mut map);
+        Self::add_step_op("cond", LispFuncType::SpecialForm, Rc::new(ops::cond), &mut map);
+        Self::add_step_op("case", LispFuncType::SpecialForm, Rc::new(ops::case), &mut map);
+        Self::add_step_op("match", LispFuncType::SpecialForm, Rc::new(ops::match_op), &mut map);
+        Self::add_step_op("let", LispFuncType::SpecialForm, Rc::new(ops::let_op), &mut map);
+        Self::add_step_op("let*", LispFuncType::SpecialForm, Rc::new(ops::let_star), &mut map);
+        Self::add_step_op("letrec", LispFuncType::SpecialForm, Rc::new(ops::letrec), &mut map);
         Self::add_op("lambda", LispFuncType::SpecialForm, Rc::new(ops::lambda), &mut map);
+        Self::add_op("doc", LispFuncType::Normal, Rc::new(ops::doc), &mut map);
+        Self::add_op("throw", LispFuncType::Normal, Rc::new(ops::throw), &mut map);
+        Self::add_op("error", LispFuncType::Normal, Rc::new(ops::error), &mut map);
+        Self::add_step_op("try", LispFuncType::SpecialForm, Rc::new(ops::try_op), &mut map);
+        Self::add_op("error?", LispFuncType::Normal, Rc::new(ops::is_error), &mut map);
+        Self::add_op("error-message", LispFuncType::Normal, Rc::new(ops::error_message), &mut map);
+        Self::add_op("error-kind", LispFuncType::Normal, Rc::new(ops::error_kind), &mut map);
+        Self::add_op("error-span", LispFuncType::Normal, Rc::new(ops::error_span), &mut map);
+        Self::add_op("error-value", LispFuncType::Normal, Rc::new(ops::error_value), &mut map);
+        Self::add_step_op("signal", LispFuncType::Normal, Rc::new(ops::signal), &mut map);
+        Self::add_step_op("handler-bind", LispFuncType::SpecialForm, Rc::new(ops::handler_bind), &mut map);
+        Self::add_step_op("restart-case", LispFuncType::SpecialForm, Rc::new(ops::restart_case), &mut map);
+        Self::add_op("invoke-restart", LispFuncType::Normal, Rc::new(ops::invoke_restart), &mut map);
+        Self::add_step_op("call/cc", LispFuncType::Normal, Rc::new(ops::call_cc), &mut map);
+        Self::add_step_op("call-with-current-continuation", LispFuncType::Normal, Rc::new(ops::call_cc), &mut map);
+        Self::add_step_op("reset", LispFuncType::SpecialForm, Rc::new(ops::reset), &mut map);
+        Self::add_step_op("shift", LispFuncType::SpecialForm, Rc::new(ops::shift), &mut map);
 
         map
     }
@@ -70,10 +189,30 @@ impl Environment {
             name.to_string(),
             Rc::new(RefCell::new(LispCell::Func(LispFunc {
                 name: name.to_string(),
-                func_type: func_type,
+                func_type,
                 func_executor: Rc::new(Box::new(FnLispFuncExecutor {
-                    op: op,
+                    op,
                 })),
+                doc: None,
+            }))),
+        );
+    }
+
+    fn add_step_op(
+        name: &'static str,
+        func_type: LispFuncType,
+        op: Rc<LispStepFn>,
+        map: &mut HashMap<String, Rc<RefCell<LispCell>>>,
+    ) {
+        map.insert(
+            name.to_string(),
+            Rc::new(RefCell::new(LispCell::Func(LispFunc {
+                name: name.to_string(),
+                func_type,
+                func_executor: Rc::new(Box::new(StepFnLispFuncExecutor {
+                    op,
+                })),
+                doc: None,
             }))),
         );
     }
@@ -88,14 +227,31 @@ impl Clone for Environment {
     }
 }
 
-type LispFn = Fn(Rc<RefCell<Environment>>, &Vec<LispCellRef>) -> LispCellRef;
+type LispFn = dyn Fn(Rc<RefCell<Environment>>, &[LispCellRef]) -> LispResult;
 
 struct FnLispFuncExecutor {
     op: Rc<LispFn>,
 }
 
 impl LispFuncExecutor for FnLispFuncExecutor {
-    fn exec(&self, env: Rc<RefCell<Environment>>, args: &Vec<LispCellRef>) -> LispCellRef {
+    fn exec(&self, env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
         (self.op)(env, args)
     }
 }
+
+/// An op that may return an expression in tail position for the evaluator to continue with.
+type LispStepFn = dyn Fn(Rc<RefCell<Environment>>, &[LispCellRef]) -> Result<LispStep, LispError>;
+
+struct StepFnLispFuncExecutor {
+    op: Rc<LispStepFn>,
+}
+
+impl LispFuncExecutor for StepFnLispFuncExecutor {
+    fn exec(&self, env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+        exec::run_step((self.op)(env, args)?)
+    }
+
+    fn exec_step(&self, env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+        (self.op)(env, args)
+    }
+}
\ No newline at end of file
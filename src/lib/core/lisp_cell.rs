@@ -1,19 +1,24 @@
 use super::*;
 
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 pub type LispCellRef = Rc<RefCell<LispCell>>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum LispCell {
     Atom(String),
-    Number(f32),
+    Number(LispNumber),
     Bool(bool),
     Str(String),
     Quoted(LispCellRef),
+    Quasiquoted(LispCellRef),
+    Unquoted(LispCellRef),
+    UnquoteSpliced(LispCellRef),
     Func(LispFunc),
     List(Rc<RefCell<LispList>>),
+    /// An error caught by `try`, which can be inspected or thrown again.
+    Error(LispError),
 }
 
 impl LispCell {
@@ -22,6 +27,104 @@ impl LispCell {
     }
 
     pub fn to_ref(self) -> LispCellRef {
+        count_allocation();
+
         Rc::new(RefCell::new(self))
     }
-}
\ No newline at end of file
+
+    /// Everything except `false` and the empty list `nil` counts as true in a condition.
+    pub fn is_truthy(&self) -> bool {
+        match *self {
+            LispCell::Bool(val) => val,
+            LispCell::List(ref list) => !list.borrow().is_empty(),
+            _ => true,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            LispCell::Atom(_) => "atom",
+            LispCell::Number(_) => "number",
+            LispCell::Bool(_) => "bool",
+            LispCell::Str(_) => "str",
+            LispCell::Quoted(_) => "quoted",
+            LispCell::Quasiquoted(_) => "quasiquoted",
+            LispCell::Unquoted(_) => "unquoted",
+            LispCell::UnquoteSpliced(_) => "unquote-spliced",
+            LispCell::Func(_) => "func",
+            LispCell::List(_) => "list",
+            LispCell::Error(_) => "error",
+        }
+    }
+}
+
+// The derived comparison recurses once per list node and nested cell, which overflows the stack on
+// long or deeply nested lists, so compare from an explicit stack of pairs still to check instead
+impl PartialEq for LispCell {
+    fn eq(&self, rhs: &Self) -> bool {
+        let mut pending = vec![];
+
+        if !shallow_eq(self, rhs, &mut pending) {
+            return false;
+        }
+
+        while let Some((lhs, rhs)) = pending.pop() {
+            if !Rc::ptr_eq(&lhs, &rhs) && !shallow_eq(&lhs.borrow(), &rhs.borrow(), &mut pending) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Compares everything about two cells except the cells inside them, which are added to `pending`
+/// to compare later.
+fn shallow_eq(lhs: &LispCell, rhs: &LispCell, pending: &mut Vec<(LispCellRef, LispCellRef)>) -> bool {
+    match (lhs, rhs) {
+        (LispCell::Atom(lhs), LispCell::Atom(rhs)) => lhs == rhs,
+        (LispCell::Number(lhs), LispCell::Number(rhs)) => lhs == rhs,
+        (&LispCell::Bool(lhs), &LispCell::Bool(rhs)) => lhs == rhs,
+        (LispCell::Str(lhs), LispCell::Str(rhs)) => lhs == rhs,
+        (LispCell::Func(lhs), LispCell::Func(rhs)) => lhs == rhs,
+        (LispCell::Error(lhs), LispCell::Error(rhs)) => lhs == rhs,
+        (&LispCell::Quoted(ref lhs), &LispCell::Quoted(ref rhs))
+        | (&LispCell::Quasiquoted(ref lhs), &LispCell::Quasiquoted(ref rhs))
+        | (&LispCell::Unquoted(ref lhs), &LispCell::Unquoted(ref rhs))
+        | (&LispCell::UnquoteSpliced(ref lhs), &LispCell::UnquoteSpliced(ref rhs)) => {
+            pending.push((lhs.clone(), rhs.clone()));
+
+            true
+        }
+        (LispCell::List(lhs), LispCell::List(rhs)) => {
+            let (lhs, rhs) = (LispList::to_vec(lhs.clone()), LispList::to_vec(rhs.clone()));
+
+            if lhs.len() != rhs.len() {
+                return false;
+            }
+
+            pending.extend(lhs.into_iter().zip(rhs));
+
+            true
+        }
+        _ => false,
+    }
+}
+
+thread_local! {
+    static ALLOCATED_CELLS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The number of cells, list nodes and continuation frames allocated on this thread so far, which
+/// the evaluator uses to enforce `ExecOptions::max_cells`.
+pub fn allocated_cells() -> usize {
+    ALLOCATED_CELLS.with(|count| count.get())
+}
+
+pub fn count_allocation() {
+    count_allocations(1);
+}
+
+pub fn count_allocations(count: usize) {
+    ALLOCATED_CELLS.with(|allocated| allocated.set(allocated.get() + count));
+}
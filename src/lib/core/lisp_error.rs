@@ -0,0 +1,284 @@
+use super::*;
+
+use print::print_cell;
+
+use std::error::Error;
+use std::time::Duration;
+
+pub type LispResult = Result<LispCellRef, LispError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LispErrorKind {
+    Parse(String),
+    UnboundSymbol(String),
+    Arity {
+        name: String,
+        expected: String,
+        found: usize,
+    },
+    Type {
+        expected: String,
+        found: String,
+    },
+    NotAFunction(String),
+    /// A `match` form had no clause for the given value.
+    NoMatch(String),
+    DivisionByZero,
+    /// A special form or reader construct used in a way that doesn't make sense.
+    Syntax(String),
+    User(LispCellRef),
+    /// `invoke-restart` was called with the name of a restart that isn't active.
+    NoRestart(String),
+    /// Control passing to the innermost active restart with the given name, taking the given args.
+    /// It unwinds the stack like an error, but only `finally` clauses on the way see it.
+    RestartInvoked {
+        name: String,
+        args: Vec<LispCellRef>,
+    },
+    Io(String),
+    /// Evaluation needed more than the given number of stack frames.
+    StackDepthExceeded(usize),
+    /// Evaluation took more than the given number of steps.
+    StepLimitExceeded(u64),
+    /// Evaluation allocated more than the given number of cells.
+    CellLimitExceeded(usize),
+    /// Evaluation ran for longer than the given time.
+    Timeout(Duration),
+    /// Exact arithmetic was asked to work with numbers of more than the given number of bits.
+    NumberTooLarge(u64),
+}
+
+impl LispErrorKind {
+    /// The name `try` catches this kind of error by, as in `(catch :type e ...)`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LispErrorKind::Parse(_) => "parse",
+            LispErrorKind::UnboundSymbol(_) => "unbound-symbol",
+            LispErrorKind::Arity { .. } => "arity",
+            LispErrorKind::Type { .. } => "type",
+            LispErrorKind::NotAFunction(_) => "not-a-function",
+            LispErrorKind::NoMatch(_) => "no-match",
+            LispErrorKind::DivisionByZero => "division-by-zero",
+            LispErrorKind::Syntax(_) => "syntax",
+            LispErrorKind::User(_) => "user",
+            LispErrorKind::NoRestart(_) => "no-restart",
+            LispErrorKind::RestartInvoked { .. } => "restart",
+            LispErrorKind::Io(_) => "io",
+            LispErrorKind::StackDepthExceeded(_) => "stack-depth-exceeded",
+            LispErrorKind::StepLimitExceeded(_) => "step-limit-exceeded",
+            LispErrorKind::CellLimitExceeded(_) => "cell-limit-exceeded",
+            LispErrorKind::Timeout(_) => "timeout",
+            LispErrorKind::NumberTooLarge(_) => "number-too-large",
+        }
+    }
+
+    /// Whether a program may catch this kind of error. Running out of one of the limits in
+    /// `ExecOptions` always ends evaluation, so programs can't use `try` to outlast them.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            *self,
+            LispErrorKind::RestartInvoked { .. }
+                | LispErrorKind::StackDepthExceeded(_)
+                | LispErrorKind::StepLimitExceeded(_)
+                | LispErrorKind::CellLimitExceeded(_)
+                | LispErrorKind::Timeout(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LispError {
+    pub kind: LispErrorKind,
+    pub span: Option<Span>,
+    /// The innermost form that was being evaluated when the error was raised.
+    pub form: Option<LispCellRef>,
+}
+
+impl LispError {
+    pub fn new(kind: LispErrorKind) -> LispError {
+        LispError {
+            kind,
+            span: None,
+            form: None,
+        }
+    }
+
+    pub fn parse(msg: &str, span: Span) -> LispError {
+        LispError::new(LispErrorKind::Parse(msg.to_string())).with_span(span)
+    }
+
+    pub fn unbound_symbol(symbol: &str) -> LispError {
+        LispError::new(LispErrorKind::UnboundSymbol(symbol.to_string()))
+    }
+
+    pub fn arity(name: &str, expected: &str, found: usize) -> LispError {
+        LispError::new(LispErrorKind::Arity {
+            name: name.to_string(),
+            expected: expected.to_string(),
+            found,
+        })
+    }
+
+    pub fn type_error(expected: &str, found: &LispCellRef) -> LispError {
+        LispError::new(LispErrorKind::Type {
+            expected: expected.to_string(),
+            found: describe_cell(found),
+        })
+    }
+
+    pub fn not_a_function(found: &LispCellRef) -> LispError {
+        LispError::new(LispErrorKind::NotAFunction(describe_cell(found)))
+    }
+
+    pub fn no_match(value: &LispCellRef) -> LispError {
+        LispError::new(LispErrorKind::NoMatch(describe_cell(value)))
+    }
+
+    pub fn division_by_zero() -> LispError {
+        LispError::new(LispErrorKind::DivisionByZero)
+    }
+
+    pub fn syntax(msg: &str) -> LispError {
+        LispError::new(LispErrorKind::Syntax(msg.to_string()))
+    }
+
+    pub fn user(value: LispCellRef) -> LispError {
+        LispError::new(LispErrorKind::User(value))
+    }
+
+    pub fn no_restart(name: &str) -> LispError {
+        LispError::new(LispErrorKind::NoRestart(name.to_string()))
+    }
+
+    pub fn restart_invoked(name: &str, args: Vec<LispCellRef>) -> LispError {
+        LispError::new(LispErrorKind::RestartInvoked {
+            name: name.to_string(),
+            args,
+        })
+    }
+
+    pub fn stack_depth_exceeded(max_depth: usize) -> LispError {
+        LispError::new(LispErrorKind::StackDepthExceeded(max_depth))
+    }
+
+    pub fn step_limit_exceeded(max_steps: u64) -> LispError {
+        LispError::new(LispErrorKind::StepLimitExceeded(max_steps))
+    }
+
+    pub fn cell_limit_exceeded(max_cells: usize) -> LispError {
+        LispError::new(LispErrorKind::CellLimitExceeded(max_cells))
+    }
+
+    pub fn timeout(timeout: Duration) -> LispError {
+        LispError::new(LispErrorKind::Timeout(timeout))
+    }
+
+    pub fn number_too_large(max_bits: u64) -> LispError {
+        LispError::new(LispErrorKind::NumberTooLarge(max_bits))
+    }
+
+    pub fn with_span(mut self, span: Span) -> LispError {
+        self.span = Some(span);
+        self
+    }
+
+    /// Records `form` as the source of this error unless a more specific form was already recorded.
+    pub fn with_form(mut self, form: &LispCellRef) -> LispError {
+        if self.form.is_none() {
+            self.form = Some(form.clone());
+        }
+
+        self
+    }
+
+    /// Renders the error along with the line of `source` it points at, underlining the offending span.
+    pub fn render(&self, source: &str) -> String {
+        let span = match self.span {
+            Some(span) => span,
+            None => return self.to_string(),
+        };
+
+        let line_start = source[..span.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[span.start..].find('\n').map(|i| span.start + i).unwrap_or(source.len());
+        let line = source[line_start..line_end].trim_end_matches('\r');
+
+        // Only underline the first line of a multi-line span
+        let underline_end = span.end.min(line_start + line.len());
+        let underline_len = match underline_end > span.start {
+            true => source[span.start..underline_end].chars().count(),
+            false => 1,
+        };
+
+        let gutter = span.line.to_string();
+        let padding = " ".repeat(gutter.len());
+
+        format!(
+            "{}\n{} |\n{} | {}\n{} | {}{}",
+            self,
+            padding,
+            gutter,
+            line,
+            padding,
+            " ".repeat(span.col - 1),
+            "^".repeat(underline_len)
+        )
+    }
+}
+
+impl fmt::Display for LispErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LispErrorKind::Parse(ref msg) => write!(f, "parse error: {}", msg),
+            LispErrorKind::UnboundSymbol(ref symbol) => write!(f, "unbound symbol: {}", symbol),
+            LispErrorKind::Arity {
+                ref name,
+                ref expected,
+                found,
+            } => write!(f, "wrong number of args passed to {} (expected {}, found {})", name, expected, found),
+            LispErrorKind::Type {
+                ref expected,
+                ref found,
+            } => write!(f, "type error: expected {}, found {}", expected, found),
+            LispErrorKind::NotAFunction(ref found) => write!(f, "not a function: {}", found),
+            LispErrorKind::NoMatch(ref found) => write!(f, "no match clause for {}", found),
+            LispErrorKind::DivisionByZero => write!(f, "division by zero"),
+            LispErrorKind::Syntax(ref msg) => write!(f, "syntax error: {}", msg),
+            LispErrorKind::User(ref value) => write!(f, "error: {}", print_cell(value.clone())),
+            LispErrorKind::NoRestart(ref name) => write!(f, "no active restart named {}", name),
+            LispErrorKind::RestartInvoked {
+                ref name, ..
+            } => write!(f, "restart {} invoked outside of its restart-case", name),
+            LispErrorKind::Io(ref msg) => write!(f, "io error: {}", msg),
+            LispErrorKind::StackDepthExceeded(max_depth) => {
+                write!(f, "stack depth exceeded: evaluation needed more than {} frames", max_depth)
+            }
+            LispErrorKind::StepLimitExceeded(max_steps) => {
+                write!(f, "step limit exceeded: evaluation took more than {} steps", max_steps)
+            }
+            LispErrorKind::CellLimitExceeded(max_cells) => {
+                write!(f, "cell limit exceeded: evaluation allocated more than {} cells", max_cells)
+            }
+            LispErrorKind::Timeout(timeout) => write!(f, "timed out: evaluation ran for more than {:?}", timeout),
+            LispErrorKind::NumberTooLarge(max_bits) => {
+                write!(f, "number too large: exact arithmetic is limited to {} bits", max_bits)
+            }
+        }
+    }
+}
+
+impl fmt::Display for LispError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} (line {}, col {})", self.kind, span.line, span.col),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl Error for LispError {}
+
+fn describe_cell(cell: &LispCellRef) -> String {
+    let type_name = cell.borrow().type_name();
+
+    format!("{} {}", type_name, print_cell(cell.clone()))
+}
@@ -3,21 +3,146 @@ use super::*;
 pub struct LispFunc {
     pub name: String,
     pub func_type: LispFuncType,
-    pub func_executor: Rc<Box<LispFuncExecutor>>,
+    pub func_executor: Rc<Box<dyn LispFuncExecutor>>,
+    /// The docstring given when the function was defined, if any
+    pub doc: Option<String>,
 }
 
 impl LispFunc {
-    pub fn new(name: String, func_type: LispFuncType, func_executor: Box<LispFuncExecutor>) -> LispFunc {
+    pub fn new(name: String, func_type: LispFuncType, func_executor: Box<dyn LispFuncExecutor>) -> LispFunc {
         LispFunc {
-            name: name,
-            func_type: func_type,
+            name,
+            func_type,
             func_executor: Rc::new(func_executor),
+            doc: None,
         }
     }
+
+    pub fn with_doc(mut self, doc: Option<String>) -> LispFunc {
+        self.doc = doc;
+        self
+    }
 }
 
 pub trait LispFuncExecutor {
-    fn exec(&self, env: Rc<RefCell<Environment>>, args: &Vec<LispCellRef>) -> LispCellRef;
+    fn exec(&self, env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult;
+
+    /// Like `exec`, but may hand an expression in tail position back to the evaluator instead of
+    /// evaluating it, so that tail calls don't grow the Rust stack.
+    fn exec_step(&self, env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+        self.exec(env, args).map(LispStep::Done)
+    }
+}
+
+/// What the evaluator should do with the value of an `EvalThen` expression.
+pub type LispContinuation = Rc<dyn Fn(LispCellRef) -> Result<LispStep, LispError>>;
+
+/// What the evaluator should do with an error raised inside a `Try` or `Handle` step.
+pub type LispCatcher = Rc<dyn Fn(LispError) -> Result<LispStep, LispError>>;
+
+/// Which errors unwinding the stack a `Try` step's catcher is handed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LispCatches {
+    /// Any error that can be caught.
+    Errors,
+    /// Catchable errors and restart invocations, for a catcher that cleans up and then raises them
+    /// again. Unlike `Errors`, this doesn't keep `Handle` handlers outside it from seeing errors.
+    Unwinding,
+    /// Invocations of the restarts with these names.
+    Restarts(Vec<String>),
+}
+
+impl LispCatches {
+    pub fn catches(&self, kind: &LispErrorKind) -> bool {
+        match (self, kind) {
+            (LispCatches::Restarts(names), LispErrorKind::RestartInvoked { name, .. }) => {
+                names.contains(name)
+            }
+            (&LispCatches::Restarts(_), _) => false,
+            (&LispCatches::Unwinding, &LispErrorKind::RestartInvoked { .. }) => true,
+            (_, kind) => kind.is_catchable(),
+        }
+    }
+}
+
+/// The result of applying a function: either a finished value or an expression still to be
+/// evaluated in the given environment.
+///
+/// Ops that need the value of a subexpression return `EvalThen` rather than calling `exec`, so the
+/// evaluator keeps the rest of the op on its own stack instead of the Rust one.
+pub enum LispStep {
+    Done(LispCellRef),
+    TailCall(Rc<RefCell<Environment>>, LispCellRef),
+    EvalThen(Rc<RefCell<Environment>>, LispCellRef, LispContinuation),
+    /// Runs the inner step, handing any error it raises that the `LispCatches` covers to the
+    /// catcher once the stack has unwound, whose result takes the place of the inner step's.
+    Try(Box<LispStep>, LispCatches, LispCatcher),
+    /// Runs the inner step, handing each catchable error it raises and condition it signals to the
+    /// handler before anything unwinds. The handler's value is ignored, so the error carries on
+    /// unwinding afterwards unless the handler invokes a restart.
+    Handle(Box<LispStep>, LispCatcher),
+    /// Hands the condition to the `Handle` handlers around it in turn, then finishes with nil.
+    Signal(LispError),
+    /// Passes the rest of the evaluation on to the continuation, as a function that carries on
+    /// from here with the value it's called with.
+    CallCC(LispContinuation),
+    /// Runs the inner step inside a delimiter for `Shift`.
+    Reset(Box<LispStep>),
+    /// Like `CallCC`, but only passes on the rest of the evaluation up to the innermost `Reset`,
+    /// which the continuation's step then takes the place of.
+    Shift(LispContinuation),
+    /// Carries on from where a continuation was captured with the given value.
+    Resume(Rc<exec::Continuation>, LispCellRef),
+    /// Runs the inner step, then passes its value on to the continuation.
+    Then(Box<LispStep>, LispContinuation),
+}
+
+impl LispStep {
+    pub fn eval_then<F>(env: Rc<RefCell<Environment>>, cell: LispCellRef, then: F) -> LispStep
+    where
+        F: Fn(LispCellRef) -> Result<LispStep, LispError> + 'static,
+    {
+        LispStep::EvalThen(env, cell, Rc::new(then))
+    }
+
+    pub fn try_catch<F>(body: LispStep, catch: F) -> LispStep
+    where
+        F: Fn(LispError) -> Result<LispStep, LispError> + 'static,
+    {
+        LispStep::Try(Box::new(body), LispCatches::Errors, Rc::new(catch))
+    }
+
+    pub fn handle<F>(body: LispStep, handle: F) -> LispStep
+    where
+        F: Fn(LispError) -> Result<LispStep, LispError> + 'static,
+    {
+        LispStep::Handle(Box::new(body), Rc::new(handle))
+    }
+
+    /// Passes the eventual value of this step on to `then`.
+    pub fn and_then<F>(self, then: F) -> Result<LispStep, LispError>
+    where
+        F: Fn(LispCellRef) -> Result<LispStep, LispError> + 'static,
+    {
+        Self::chain(self, Rc::new(then))
+    }
+
+    fn chain(step: LispStep, then: LispContinuation) -> Result<LispStep, LispError> {
+        match step {
+            LispStep::Done(value) => then(value),
+            LispStep::TailCall(env, cell) => Ok(LispStep::EvalThen(env, cell, then)),
+            LispStep::EvalThen(env, cell, first) => Ok(LispStep::eval_then(env, cell, move |value| {
+                Self::chain(first(value)?, then.clone())
+            })),
+            LispStep::Then(inner, first) => Ok(LispStep::Then(
+                inner,
+                Rc::new(move |value| Self::chain(first(value)?, then.clone())),
+            )),
+            // `then` mustn't run inside a try or handler, or they would see its errors too, and
+            // continuations captured by the step need to include it
+            step => Ok(LispStep::Then(Box::new(step), then)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -45,6 +170,7 @@ impl Clone for LispFunc {
             name: self.name.clone(),
             func_type: self.func_type.clone(),
             func_executor: self.func_executor.clone(),
+            doc: self.doc.clone(),
         }
     }
 }
\ No newline at end of file
@@ -2,15 +2,22 @@ use super::*;
 
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::mem;
 
-type LispListRef = Rc<RefCell<LispList>>;
+pub type LispListRef = Rc<RefCell<LispList>>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct LispList {
     value: Option<LispCellRef>,
     next: Option<LispListRef>,
 }
 
+impl Default for LispList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LispList {
     pub fn new() -> LispList {
         Self::from_vec(vec![])
@@ -24,7 +31,7 @@ impl LispList {
     }
 
     pub fn from_vec(vec: Vec<LispCellRef>) -> LispList {
-        if vec.len() == 0 {
+        if vec.is_empty() {
             return LispList {
                 value: None,
                 next: None,
@@ -64,31 +71,35 @@ impl LispList {
     }
 
     pub fn to_ref(self) -> LispListRef {
+        count_allocation();
+
         Rc::new(RefCell::new(self))
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.value.is_none() && self.next.is_none()
+    }
+
     pub fn get_value(&self) -> Option<LispCellRef> {
         self.value.clone()
     }
 
     pub fn to_vec(list: LispListRef) -> Vec<LispCellRef> {
-        let mut results = vec![];
-        let mut current = Some(list);
+        let list = list.borrow();
 
-        loop {
-            match current {
-                None => break,
-                Some(node) => {
-                    let borrowed_node = node.borrow();
+        list.values()
+    }
 
-                    match borrowed_node.get_value() {
-                        Some(value) => results.push(value.clone()),
-                        None => {}
-                    };
+    /// The values of this node and the ones after it.
+    pub fn values(&self) -> Vec<LispCellRef> {
+        let mut results: Vec<LispCellRef> = self.value.iter().cloned().collect();
+        let mut current = self.next.clone();
 
-                    current = borrowed_node.next.clone();
-                }
-            }
+        while let Some(node) = current {
+            let borrowed_node = node.borrow();
+
+            results.extend(borrowed_node.get_value());
+            current = borrowed_node.next.clone();
         }
 
         results
@@ -111,6 +122,98 @@ impl LispList {
     }
 }
 
+// Lists are equal when their values are, which `LispCell`'s comparison checks without recursing
+impl PartialEq for LispList {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.values() == rhs.values()
+    }
+}
+
+// The default drops of lists and cells recurse once per list node and nested cell (through `next`,
+// nested lists and quotes), which overflows the stack on long or deeply nested values. Instead,
+// whatever they hold that nothing else refers to is emptied out and queued up to be unlinked
+// iteratively, leaving only shallow values behind to be dropped normally.
+impl Drop for LispList {
+    fn drop(&mut self) {
+        let mut cells = vec![];
+        let mut lists = vec![];
+
+        detach_list(self, &mut cells, &mut lists);
+        unlink(cells, lists);
+    }
+}
+
+impl Drop for LispCell {
+    fn drop(&mut self) {
+        let mut cells = vec![];
+        let mut lists = vec![];
+
+        detach_cell(self, &mut cells, &mut lists);
+        unlink(cells, lists);
+    }
+}
+
+fn unlink(mut cells: Vec<LispCell>, mut lists: Vec<LispListRef>) {
+    loop {
+        if let Some(list) = lists.pop() {
+            detach_list(&mut list.borrow_mut(), &mut cells, &mut lists);
+        } else if let Some(mut cell) = cells.pop() {
+            detach_cell(&mut cell, &mut cells, &mut lists);
+        } else {
+            break;
+        }
+    }
+}
+
+/// Takes everything out of `list` that nothing else refers to and that holds other values.
+fn detach_list(list: &mut LispList, cells: &mut Vec<LispCell>, lists: &mut Vec<LispListRef>) {
+    if let Some(value) = list.value.take() {
+        detach_nested(&value, cells);
+    }
+
+    if let Some(next) = list.next.take() {
+        if Rc::strong_count(&next) == 1 {
+            lists.push(next);
+        }
+    }
+}
+
+/// Takes everything out of `cell` that nothing else refers to and that holds other values.
+fn detach_cell(cell: &mut LispCell, cells: &mut Vec<LispCell>, lists: &mut Vec<LispListRef>) {
+    match *cell {
+        LispCell::List(ref list) if Rc::strong_count(list) == 1 => {
+            detach_list(&mut list.borrow_mut(), cells, lists);
+        }
+        LispCell::Quoted(ref quoted)
+        | LispCell::Quasiquoted(ref quoted)
+        | LispCell::Unquoted(ref quoted)
+        | LispCell::UnquoteSpliced(ref quoted) => detach_nested(quoted, cells),
+        _ => {}
+    }
+}
+
+/// Queues up the contents of `cell` to be unlinked if nothing else refers to it and it holds other
+/// values, leaving a plain value behind in its place. Other cells are just dropped, which is all
+/// most drops need, so they don't allocate.
+fn detach_nested(cell: &LispCellRef, cells: &mut Vec<LispCell>) {
+    if Rc::strong_count(cell) != 1 {
+        return;
+    }
+
+    let is_nested = matches!(
+        *cell.borrow(),
+        LispCell::List(_)
+            | LispCell::Quoted(_)
+            | LispCell::Quasiquoted(_)
+            | LispCell::Unquoted(_)
+            | LispCell::UnquoteSpliced(_)
+    );
+
+    if is_nested {
+        cells.push(mem::replace(&mut *cell.borrow_mut(), LispCell::Bool(false)));
+    }
+}
+
 #[cfg(test)]
 mod test {
     use util;
@@ -118,9 +221,27 @@ mod test {
 
     #[test]
     fn create_list_from_vec() {
-        let list_contents = vec![util::make_num(1f32)];
+        let list_contents = vec![util::make_int(1)];
         let list = LispList::from_vec(list_contents.clone()).to_ref();
 
         assert_eq!(LispList::to_vec(list), list_contents);
     }
+
+    #[test]
+    fn drop_long_and_deep_lists() {
+        let long_list = LispList::from_vec((0..200_000).map(util::make_int).collect());
+        drop(long_list);
+
+        let mut deep_list = util::make_list(vec![]);
+        for _ in 0..200_000 {
+            deep_list = util::make_quoted(util::make_list(vec![deep_list]));
+        }
+        drop(deep_list);
+
+        let mut deep_quote = util::make_list(vec![]);
+        for _ in 0..200_000 {
+            deep_quote = util::make_quoted(deep_quote);
+        }
+        drop(deep_quote);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,463 @@
+use super::*;
+
+use std::cmp::Ordering;
+
+/// The most bits the exact operands of an arithmetic op may have between them, counting both parts
+/// of ratios. Bignum multiplication is quadratic, so this bounds how long any one op can take, and
+/// larger results are refused with `LispErrorKind::NumberTooLarge` rather than computed. Integer
+/// literals are held to it as well.
+pub const MAX_EXACT_BITS: u64 = 1 << 18;
+
+/// A number in ruspt's numeric tower. Exact numbers are always kept in their simplest form: integers
+/// that fit in an `i64` are `Int`s, and ratios are in lowest terms with a denominator above 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LispNumber {
+    Int(i64),
+    /// An integer outside the `i64` range.
+    BigInt(BigInt),
+    /// An exact fraction as a numerator and a positive denominator.
+    Ratio(BigInt, BigInt),
+    Float(f64),
+}
+
+impl LispNumber {
+    pub fn from_bigint(num: BigInt) -> LispNumber {
+        match num.to_i64() {
+            Some(num) => LispNumber::Int(num),
+            None => LispNumber::BigInt(num),
+        }
+    }
+
+    /// Makes the exact number `numerator / denominator`, reduced to its simplest form.
+    pub fn ratio(numerator: BigInt, denominator: BigInt) -> Result<LispNumber, LispError> {
+        if denominator.is_zero() {
+            return Err(LispError::division_by_zero());
+        }
+
+        let gcd = numerator.gcd(&denominator);
+        let (mut numerator, _) = numerator.div_rem(&gcd).expect("gcd is non-zero");
+        let (mut denominator, _) = denominator.div_rem(&gcd).expect("gcd is non-zero");
+
+        if denominator.is_negative() {
+            numerator = -&numerator;
+            denominator = -&denominator;
+        }
+
+        match denominator.is_one() {
+            true => Ok(LispNumber::from_bigint(numerator)),
+            false => Ok(LispNumber::Ratio(numerator, denominator)),
+        }
+    }
+
+    /// Reads a number literal: an integer (`1`, `-20`), a ratio (`3/4`), a float (`1.0`, `1e10`) or
+    /// an integer in another radix (`#x1F`, `#o17`, `#b101`).
+    ///
+    /// Returns `Ok(None)` for words that aren't numbers at all, and an error message for ones that
+    /// look like numbers but are malformed.
+    pub fn parse(word: &str) -> Result<Option<LispNumber>, &'static str> {
+        if word.starts_with('#') {
+            let radix = match word.chars().nth(1) {
+                Some('x') | Some('X') => 16,
+                Some('o') | Some('O') => 8,
+                Some('b') | Some('B') => 2,
+                _ => return Ok(None),
+            };
+
+            return match parse_integer(&word[2..], radix)? {
+                Some(num) => Ok(Some(LispNumber::from_bigint(num))),
+                None => Err("Invalid program: malformed number literal"),
+            };
+        }
+
+        let unsigned = word.trim_start_matches(['+', '-']);
+        if word.len() - unsigned.len() > 1 || !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            return Ok(None);
+        }
+
+        if let Some(num) = parse_integer(word, 10)? {
+            return Ok(Some(LispNumber::from_bigint(num)));
+        }
+
+        if let Some(slash) = word.find('/') {
+            let numerator = parse_integer(&word[..slash], 10)?;
+            let denominator = match word[slash + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+                true => parse_integer(&word[slash + 1..], 10)?,
+                false => None,
+            };
+
+            return match (numerator, denominator) {
+                (Some(numerator), Some(denominator)) => match LispNumber::ratio(numerator, denominator) {
+                    Ok(ratio) => Ok(Some(ratio)),
+                    Err(_) => Err("Invalid program: zero denominator in ratio"),
+                },
+                _ => Ok(None),
+            };
+        }
+
+        // Rust also accepts words like `inf` and `NaN`, but those never get this far
+        let is_float = unsigned.chars().all(|c| c.is_ascii_digit() || ".eE+-".contains(c));
+        match word.parse::<f64>() {
+            Ok(num) if is_float => Ok(Some(LispNumber::Float(num))),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        !matches!(*self, LispNumber::Float(_))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            LispNumber::Int(_) | LispNumber::BigInt(_) => true,
+            LispNumber::Ratio(..) => false,
+            LispNumber::Float(num) => num.is_finite() && num.fract() == 0f64,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            LispNumber::Int(num) => num as f64,
+            LispNumber::BigInt(ref num) => num.to_f64(),
+            LispNumber::Ratio(ref numerator, ref denominator) => numerator.to_f64() / denominator.to_f64(),
+            LispNumber::Float(num) => num,
+        }
+    }
+
+    /// The numerator and denominator of an exact number.
+    fn to_ratio_parts(&self) -> (BigInt, BigInt) {
+        match *self {
+            LispNumber::Int(num) => (BigInt::from_i64(num), BigInt::from_i64(1)),
+            LispNumber::BigInt(ref num) => (num.clone(), BigInt::from_i64(1)),
+            LispNumber::Ratio(ref numerator, ref denominator) => (numerator.clone(), denominator.clone()),
+            LispNumber::Float(_) => panic!("Floats don't have exact ratio parts"),
+        }
+    }
+
+    pub fn add(&self, other: &LispNumber) -> Result<LispNumber, LispError> {
+        self.combine(
+            other,
+            i64::checked_add,
+            |(a, b), (c, d)| LispNumber::ratio(&(&a * &d) + &(&c * &b), &b * &d),
+            |a, b| a + b,
+        )
+    }
+
+    pub fn sub(&self, other: &LispNumber) -> Result<LispNumber, LispError> {
+        self.combine(
+            other,
+            i64::checked_sub,
+            |(a, b), (c, d)| LispNumber::ratio(&(&a * &d) - &(&c * &b), &b * &d),
+            |a, b| a - b,
+        )
+    }
+
+    pub fn mul(&self, other: &LispNumber) -> Result<LispNumber, LispError> {
+        self.combine(other, i64::checked_mul, |(a, b), (c, d)| LispNumber::ratio(&a * &c, &b * &d), |a, b| a * b)
+    }
+
+    /// Divides exactly when both numbers are exact, so `(/ 1 3)` is the ratio `1/3`.
+    pub fn div(&self, other: &LispNumber) -> Result<LispNumber, LispError> {
+        if other.is_exact() && other.to_ratio_parts().0.is_zero() {
+            return match self.is_exact() {
+                true => Err(LispError::division_by_zero()),
+                false => Ok(LispNumber::Float(self.to_f64() / 0f64)),
+            };
+        }
+
+        self.combine(other, |_, _| None, |(a, b), (c, d)| LispNumber::ratio(&a * &d, &b * &c), |a, b| a / b)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match *self {
+            LispNumber::Int(num) => num == 0,
+            LispNumber::Float(num) => num == 0f64,
+            // Big integers and ratios are never zero once normalized
+            _ => false,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match *self {
+            LispNumber::Int(num) => num < 0,
+            LispNumber::BigInt(ref num) | LispNumber::Ratio(ref num, _) => num.is_negative(),
+            LispNumber::Float(num) => num < 0f64,
+        }
+    }
+
+    /// Compares numerically, so `1` and `1.0` are equal. Returns `None` if either number is NaN.
+    ///
+    /// Comparing a ratio takes a multiplication, so it's refused past `MAX_EXACT_BITS` like one.
+    pub fn compare(&self, other: &LispNumber) -> Result<Option<Ordering>, LispError> {
+        if !self.is_exact() || !other.is_exact() {
+            return Ok(self.to_f64().partial_cmp(&other.to_f64()));
+        }
+
+        if let (&LispNumber::Int(a), &LispNumber::Int(b)) = (self, other) {
+            return Ok(Some(a.cmp(&b)));
+        }
+
+        let (a, b) = self.to_ratio_parts();
+        let (c, d) = other.to_ratio_parts();
+
+        if self.is_integer() && other.is_integer() {
+            return Ok(Some(a.cmp(&c)));
+        }
+
+        if self.exact_bits() + other.exact_bits() > MAX_EXACT_BITS {
+            return Err(LispError::number_too_large(MAX_EXACT_BITS));
+        }
+
+        // Denominators are always positive, so cross-multiplying keeps the order
+        Ok(Some((&a * &d).cmp(&(&c * &b))))
+    }
+
+    pub fn neg(&self) -> LispNumber {
+        match *self {
+            LispNumber::Int(num) => match num.checked_neg() {
+                Some(num) => LispNumber::Int(num),
+                None => LispNumber::BigInt(-&BigInt::from_i64(num)),
+            },
+            LispNumber::BigInt(ref num) => LispNumber::from_bigint(-num),
+            LispNumber::Ratio(ref numerator, ref denominator) => LispNumber::Ratio(-numerator, denominator.clone()),
+            LispNumber::Float(num) => LispNumber::Float(-num),
+        }
+    }
+
+    pub fn abs(&self) -> LispNumber {
+        match self.is_negative() {
+            true => self.neg(),
+            false => self.clone(),
+        }
+    }
+
+    pub fn to_float(&self) -> LispNumber {
+        LispNumber::Float(self.to_f64())
+    }
+
+    /// Truncating division of two integers, returning the quotient and a remainder with the sign of
+    /// `self`.
+    pub fn div_rem(&self, other: &LispNumber) -> Result<(LispNumber, LispNumber), LispError> {
+        if other.is_zero() {
+            return Err(LispError::division_by_zero());
+        }
+
+        if !self.is_exact() || !other.is_exact() {
+            let (a, b) = (self.to_f64(), other.to_f64());
+
+            return Ok((LispNumber::Float((a / b).trunc()), LispNumber::Float(a % b)));
+        }
+
+        if let (&LispNumber::Int(a), &LispNumber::Int(b)) = (self, other) {
+            if let (Some(quotient), Some(remainder)) = (a.checked_div(b), a.checked_rem(b)) {
+                return Ok((LispNumber::Int(quotient), LispNumber::Int(remainder)));
+            }
+        }
+
+        if self.exact_bits() + other.exact_bits() > MAX_EXACT_BITS {
+            return Err(LispError::number_too_large(MAX_EXACT_BITS));
+        }
+
+        let (quotient, remainder) =
+            self.to_ratio_parts().0.div_rem(&other.to_ratio_parts().0).expect("divisor is non-zero");
+
+        Ok((LispNumber::from_bigint(quotient), LispNumber::from_bigint(remainder)))
+    }
+
+    /// The remainder of flooring division of two integers, which has the sign of `other`.
+    pub fn modulo(&self, other: &LispNumber) -> Result<LispNumber, LispError> {
+        let (_, remainder) = self.div_rem(other)?;
+
+        match !remainder.is_zero() && remainder.is_negative() != other.is_negative() {
+            true => remainder.add(other),
+            false => Ok(remainder),
+        }
+    }
+
+    pub fn floor(&self) -> LispNumber {
+        match *self {
+            LispNumber::Int(_) | LispNumber::BigInt(_) => self.clone(),
+            LispNumber::Ratio(ref numerator, ref denominator) => {
+                let (quotient, _) = numerator.div_rem(denominator).expect("denominator is non-zero");
+
+                // Ratios are never whole, so truncating rounded negative ones up
+                match numerator.is_negative() {
+                    true => LispNumber::from_bigint(&quotient - &BigInt::from_i64(1)),
+                    false => LispNumber::from_bigint(quotient),
+                }
+            }
+            LispNumber::Float(num) => LispNumber::Float(num.floor()),
+        }
+    }
+
+    pub fn ceil(&self) -> LispNumber {
+        self.neg().floor().neg()
+    }
+
+    /// Rounds to the nearest integer, with ties going to the even one.
+    pub fn round(&self) -> LispNumber {
+        match *self {
+            LispNumber::Int(_) | LispNumber::BigInt(_) => self.clone(),
+            LispNumber::Ratio(ref numerator, ref denominator) => {
+                let (floor, ceil) = (self.floor(), self.ceil());
+                let (_, mut remainder) = numerator.div_rem(denominator).expect("denominator is non-zero");
+
+                // The fractional part is `remainder / denominator` once it's made positive, so it's
+                // compared with a half by doubling it
+                if remainder.is_negative() {
+                    remainder = &remainder + denominator;
+                }
+
+                match (&remainder + &remainder).cmp(denominator) {
+                    Ordering::Less => floor,
+                    Ordering::Greater => ceil,
+                    Ordering::Equal => match floor.modulo(&LispNumber::Int(2)).map(|parity| parity.is_zero()) {
+                        Ok(true) => floor,
+                        _ => ceil,
+                    },
+                }
+            }
+            LispNumber::Float(num) => match (num - num.trunc()).abs() == 0.5 {
+                true => LispNumber::Float(2f64 * (num / 2f64).round()),
+                false => LispNumber::Float(num.round()),
+            },
+        }
+    }
+
+    /// Square roots of exact perfect squares stay exact, everything else gives a float.
+    pub fn sqrt(&self) -> LispNumber {
+        if let LispNumber::Int(num) = *self {
+            if num >= 0 {
+                let mut root = (num as f64).sqrt() as i64;
+
+                // The float estimate can be off by one either way for large numbers
+                while root.checked_mul(root).is_none_or(|square| square > num) {
+                    root -= 1;
+                }
+
+                while (root + 1).checked_mul(root + 1).is_some_and(|square| square <= num) {
+                    root += 1;
+                }
+
+                if root * root == num {
+                    return LispNumber::Int(root);
+                }
+            }
+        }
+
+        LispNumber::Float(self.to_f64().sqrt())
+    }
+
+    /// Raises `self` to `exponent`, exactly if `self` is exact and `exponent` is an exact integer.
+    ///
+    /// Exact results are never approximated: ones that would need more than `MAX_EXACT_BITS` bits
+    /// are refused with `LispErrorKind::NumberTooLarge`.
+    pub fn expt(&self, exponent: &LispNumber) -> Result<LispNumber, LispError> {
+        let is_unit = self.abs() == LispNumber::Int(1) || self.is_zero();
+
+        let exponent_int = match *exponent {
+            LispNumber::Int(exponent) if self.is_exact() => exponent,
+            // Only 0, 1 and -1 have powers this large that fit, and those only depend on the sign
+            // and parity of the exponent
+            LispNumber::BigInt(ref exponent) if self.is_exact() && is_unit => {
+                let (_, parity) = exponent.div_rem(&BigInt::from_i64(2)).expect("divisor is non-zero");
+
+                match (exponent.is_negative(), parity.is_zero()) {
+                    (false, true) => 2,
+                    (false, false) => 1,
+                    (true, true) => -2,
+                    (true, false) => -1,
+                }
+            }
+            LispNumber::BigInt(_) if self.is_exact() => return Err(LispError::number_too_large(MAX_EXACT_BITS)),
+            _ => return Ok(LispNumber::Float(self.to_f64().powf(exponent.to_f64()))),
+        };
+
+        let result_bits = (exponent_int.wrapping_abs() as u64).saturating_mul(self.exact_bits());
+
+        if !is_unit && result_bits > MAX_EXACT_BITS {
+            return Err(LispError::number_too_large(MAX_EXACT_BITS));
+        }
+
+        let mut result = LispNumber::Int(1);
+        let mut square = self.clone();
+        let mut remaining = exponent_int.wrapping_abs() as u64;
+
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result.mul(&square)?;
+            }
+
+            remaining >>= 1;
+            if remaining > 0 {
+                square = square.mul(&square)?;
+            }
+        }
+
+        match exponent_int < 0 {
+            true => LispNumber::Int(1).div(&result),
+            false => Ok(result),
+        }
+    }
+
+    /// The number of bits needed for an exact number, counting both parts of a ratio.
+    fn exact_bits(&self) -> u64 {
+        let (numerator, denominator) = self.to_ratio_parts();
+
+        numerator.bits() + denominator.bits()
+    }
+
+    /// Applies an arithmetic op following the contagion rules: any float makes the result a float,
+    /// otherwise `i64`s are used until they overflow, and everything else goes through ratios.
+    fn combine<I, R, F>(&self, other: &LispNumber, int_op: I, ratio_op: R, float_op: F) -> Result<LispNumber, LispError>
+    where
+        I: Fn(i64, i64) -> Option<i64>,
+        R: Fn((BigInt, BigInt), (BigInt, BigInt)) -> Result<LispNumber, LispError>,
+        F: Fn(f64, f64) -> f64,
+    {
+        if !self.is_exact() || !other.is_exact() {
+            return Ok(LispNumber::Float(float_op(self.to_f64(), other.to_f64())));
+        }
+
+        if let (&LispNumber::Int(a), &LispNumber::Int(b)) = (self, other) {
+            if let Some(result) = int_op(a, b) {
+                return Ok(LispNumber::Int(result));
+            }
+        }
+
+        if self.exact_bits() + other.exact_bits() > MAX_EXACT_BITS {
+            return Err(LispError::number_too_large(MAX_EXACT_BITS));
+        }
+
+        ratio_op(self.to_ratio_parts(), other.to_ratio_parts())
+    }
+}
+
+/// Parses an optionally signed integer in the given radix, refusing ones that would have more than
+/// `MAX_EXACT_BITS` bits before spending any time on converting them.
+fn parse_integer(text: &str, radix: u32) -> Result<Option<BigInt>, &'static str> {
+    let unsigned = match text.starts_with(['+', '-']) {
+        true => &text[1..],
+        false => text,
+    };
+    let digits = unsigned.trim_start_matches('0');
+
+    // Every digit after the leading one adds at least `log2(radix)` bits
+    let min_bits = digits.len().saturating_sub(1) as f64 * (radix as f64).log2();
+    if min_bits >= MAX_EXACT_BITS as f64 && digits.chars().all(|c| c.is_digit(radix)) {
+        return Err("Invalid program: number literal is too large");
+    }
+
+    Ok(BigInt::parse(text, radix))
+}
+
+impl fmt::Display for LispNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LispNumber::Int(num) => write!(f, "{}", num),
+            LispNumber::BigInt(ref num) => write!(f, "{}", num),
+            LispNumber::Ratio(ref numerator, ref denominator) => write!(f, "{}/{}", numerator, denominator),
+            // The debug format always includes a `.` or exponent, so floats read back as floats
+            LispNumber::Float(num) => write!(f, "{:?}", num),
+        }
+    }
+}
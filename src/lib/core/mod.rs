@@ -1,23 +1,83 @@
+mod bigint;
 mod lisp_cell;
 mod lisp_list;
+mod lisp_number;
 mod lisp_func;
+mod lisp_error;
+mod span;
 mod env;
 
+pub use self::bigint::*;
 pub use self::lisp_cell::*;
 pub use self::lisp_list::*;
+pub use self::lisp_number::*;
 pub use self::lisp_func::*;
+pub use self::lisp_error::*;
+pub use self::span::*;
 pub use self::env::*;
 
 use std::cell::RefCell;
 use std::fmt::{self, Debug};
 use std::rc::Rc;
 
+use super::exec;
 use super::ops;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct LispProgram {
     pub text: String,
-    pub entry: Option<Rc<RefCell<LispCell>>>,
+    pub forms: Vec<LispCellRef>,
+    pub spans: SourceMap,
+    /// Only populated when parsing with `ParseOptions::keep_comments`.
+    pub comments: Vec<LispComment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LispCommentKind {
+    /// `; ...` through the end of the line
+    Line,
+    /// `#| ... |#`, which may nest
+    Block,
+    /// `#;` followed by the form it comments out
+    Datum,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LispComment {
+    pub kind: LispCommentKind,
+    /// The comment as written, including its delimiters.
+    pub text: String,
+    pub span: Span,
+}
+
+impl LispProgram {
+    pub fn span_of(&self, cell: &LispCellRef) -> Option<Span> {
+        self.spans.span_of(cell)
+    }
+
+    /// Fills in the span of an error raised while running this program from the form that raised it.
+    pub fn locate_error(&self, err: LispError) -> LispError {
+        if err.span.is_some() {
+            return err;
+        }
+
+        let span = match err.form {
+            Some(ref form) => self.span_of(form),
+            None => None,
+        };
+
+        match span {
+            Some(span) => err.with_span(span),
+            None => err,
+        }
+    }
+}
+
+// Spans are derived from `text`, so two programs with the same text and forms are equal
+impl PartialEq for LispProgram {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.text == rhs.text && self.forms == rhs.forms
+    }
 }
 
 pub fn lisp_null() -> LispCellRef {
@@ -0,0 +1,58 @@
+use super::*;
+
+use std::collections::HashMap;
+
+/// A region of `LispProgram::text`: byte offsets plus the 1-based line/col of `start`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Maps the cells produced by `parse` back to where they came from in the source text.
+///
+/// Cells are keyed by identity, so lookups are only meaningful while the cells that were inserted
+/// are still alive (e.g. while the `LispProgram` that owns both is).
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    spans: HashMap<usize, Span>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap {
+            spans: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, cell: &LispCellRef, span: Span) {
+        self.spans.insert(cell_key(cell), span);
+    }
+
+    pub fn span_of(&self, cell: &LispCellRef) -> Option<Span> {
+        self.spans.get(&cell_key(cell)).cloned()
+    }
+
+    /// Makes every span relative to `offset` bytes further into the text.
+    pub fn rebase(&mut self, offset: usize) {
+        for span in self.spans.values_mut() {
+            span.start -= offset;
+            span.end -= offset;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+fn cell_key(cell: &LispCellRef) -> usize {
+    Rc::as_ptr(cell) as usize
+}
+
@@ -1,64 +1,1248 @@
 use super::core::*;
+use super::parse::parse;
 
 use std::cell::RefCell;
+use std::iter::Rev;
+use std::mem;
 use std::rc::Rc;
+use std::slice;
+use std::time::{Duration, Instant};
 
-pub fn exec_prog(env: Rc<RefCell<Environment>>, program: LispProgram) -> LispCellRef {
-    match program.entry {
-        Some(e) => exec_rec(env, e),
-        _ => panic!("No entry found for program!"),
+/// Evaluation settings for `exec_prog_with_options` and `exec_with_options`.
+///
+/// The step, cell and time limits are all off by default, and apply to a whole program rather than
+/// to each of its top-level forms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecOptions {
+    /// The most frames the evaluator's stack may hold before evaluation is abandoned with
+    /// `LispErrorKind::StackDepthExceeded`. Each pending call or continuation takes one frame, which
+    /// lives on the heap rather than the Rust stack.
+    pub max_stack_depth: usize,
+    /// The most expressions that may be evaluated.
+    pub max_steps: Option<u64>,
+    /// The most cells and list nodes that may be allocated. Frames kept by captured continuations,
+    /// and frames copied back out of them, count towards this too.
+    pub max_cells: Option<usize>,
+    /// How long evaluation may run for. This is only checked every `TIMEOUT_CHECK_INTERVAL` steps.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ExecOptions {
+    fn default() -> ExecOptions {
+        ExecOptions {
+            max_stack_depth: 1_000_000,
+            max_steps: None,
+            max_cells: None,
+            timeout: None,
+        }
+    }
+}
+
+const TIMEOUT_CHECK_INTERVAL: u64 = 1024;
+
+/// Evaluates each top-level form of `program` in order, returning the result of the last one.
+pub fn exec_prog(env: Rc<RefCell<Environment>>, program: LispProgram) -> LispResult {
+    exec_prog_with_options(env, program, &ExecOptions::default())
+}
+
+pub fn exec_prog_with_options(
+    env: Rc<RefCell<Environment>>,
+    program: LispProgram,
+    options: &ExecOptions,
+) -> LispResult {
+    Machine::new(options).run_prog(env, &program)
+}
+
+/// Parses `text` and evaluates it like `exec_prog_with_options`, except that the cells the parser
+/// allocates and the time it takes count against the limits in `options` too.
+pub fn parse_and_exec_with_options(
+    env: Rc<RefCell<Environment>>,
+    text: String,
+    options: &ExecOptions,
+) -> LispResult {
+    let mut machine = Machine::new(options);
+    let program = parse(text)?;
+
+    machine.budget.check_cells()?;
+    machine.run_prog(env, &program)
+}
+
+pub fn exec(env: Rc<RefCell<Environment>>, cell: LispCellRef) -> LispResult {
+    exec_with_options(env, cell, &ExecOptions::default())
+}
+
+pub fn exec_ref(env: Rc<RefCell<Environment>>, cell_ref: &LispCellRef) -> LispResult {
+    exec(env, cell_ref.clone())
+}
+
+pub fn exec_with_options(env: Rc<RefCell<Environment>>, cell: LispCellRef, options: &ExecOptions) -> LispResult {
+    Machine::new(options).run(Control::Eval(env, cell))
+}
+
+/// Runs `step` to completion.
+pub fn run_step(step: LispStep) -> LispResult {
+    let form = match step {
+        LispStep::Done(value) => return Ok(value),
+        _ => step_form(&step).unwrap_or_else(lisp_null),
+    };
+
+    let mut machine = Machine::new(&ExecOptions::default());
+    let control = machine.step(step, &form)?;
+
+    machine.run(control)
+}
+
+/// The first expression `step` will evaluate, if it evaluates any.
+fn step_form(step: &LispStep) -> Option<LispCellRef> {
+    match *step {
+        LispStep::TailCall(_, ref cell) | LispStep::EvalThen(_, ref cell, _) => Some(cell.clone()),
+        LispStep::Try(ref inner, ..)
+        | LispStep::Handle(ref inner, _)
+        | LispStep::Reset(ref inner)
+        | LispStep::Then(ref inner, _) => step_form(inner),
+        _ => None,
     }
 }
 
-pub fn exec(env: Rc<RefCell<Environment>>, cell: LispCellRef) -> LispCellRef {
-    exec_rec(env, cell)
+/// Evaluates each of `cells` in order in `env`, then passes their values on to `then`.
+pub fn eval_each<F>(env: Rc<RefCell<Environment>>, cells: Vec<LispCellRef>, then: F) -> Result<LispStep, LispError>
+where
+    F: Fn(Vec<LispCellRef>) -> Result<LispStep, LispError> + 'static,
+{
+    eval_each_from(env, Rc::new(cells), 0, None, Rc::new(then))
+}
+
+/// The values `eval_each` has collected so far, last first. Continuations can be resumed more than
+/// once, so the same values can be carried on from more than once too, and each time gets its own
+/// list that shares the values collected before it rather than adding to one they all share.
+struct Collected {
+    value: LispCellRef,
+    before: Option<Rc<Collected>>,
+}
+
+// Dropping a long list of values would otherwise recurse once per value
+impl Drop for Collected {
+    fn drop(&mut self) {
+        let mut before = self.before.take();
+
+        while let Some(collected) = before {
+            before = match Rc::try_unwrap(collected) {
+                Ok(mut collected) => collected.before.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+fn eval_each_from(
+    env: Rc<RefCell<Environment>>,
+    cells: Rc<Vec<LispCellRef>>,
+    index: usize,
+    collected: Option<Rc<Collected>>,
+    then: Rc<dyn Fn(Vec<LispCellRef>) -> Result<LispStep, LispError>>,
+) -> Result<LispStep, LispError> {
+    let cell = match cells.get(index) {
+        Some(cell) => cell.clone(),
+        None => {
+            let mut values = Vec::with_capacity(index);
+            let mut current = collected.as_ref();
+
+            while let Some(collected) = current {
+                values.push(collected.value.clone());
+                current = collected.before.as_ref();
+            }
+
+            values.reverse();
+
+            return then(values);
+        }
+    };
+
+    Ok(LispStep::eval_then(env.clone(), cell, move |value| {
+        let collected = Collected {
+            value,
+            before: collected.clone(),
+        };
+
+        eval_each_from(env.clone(), cells.clone(), index + 1, Some(Rc::new(collected)), then.clone())
+    }))
+}
+
+/// Evaluates the forms of `body` in order in `env`, leaving the last one in tail position. Unlike
+/// `eval_each`, the values of the forms before the last are dropped rather than collected.
+pub fn eval_body(env: Rc<RefCell<Environment>>, body: Rc<Vec<LispCellRef>>) -> Result<LispStep, LispError> {
+    eval_body_from(env, body, 0)
+}
+
+fn eval_body_from(
+    env: Rc<RefCell<Environment>>,
+    body: Rc<Vec<LispCellRef>>,
+    index: usize,
+) -> Result<LispStep, LispError> {
+    let cell = match body.get(index) {
+        Some(cell) => cell.clone(),
+        None => return Ok(LispStep::Done(lisp_null())),
+    };
+
+    if index + 1 == body.len() {
+        return Ok(LispStep::TailCall(env, cell));
+    }
+
+    Ok(LispStep::eval_then(env.clone(), cell, move |_| eval_body_from(env.clone(), body.clone(), index + 1)))
+}
+
+/// What the machine does next: evaluate an expression, or hand a value to the frame on top of the stack.
+enum Control {
+    Eval(Rc<RefCell<Environment>>, LispCellRef),
+    Return(LispCellRef),
+}
+
+/// The machine's continuation, one frame per pending call or op.
+#[derive(Clone)]
+enum Frame {
+    /// A call whose head and then args are being evaluated one at a time. `args` is the rest of
+    /// the call's list from the next arg to evaluate on.
+    Call {
+        env: Rc<RefCell<Environment>>,
+        form: LispCellRef,
+        function: Option<LispCellRef>,
+        args: Option<LispListRef>,
+        values: Vec<LispCellRef>,
+    },
+    /// The rest of an op, waiting on the value of an `EvalThen` expression.
+    Then {
+        form: LispCellRef,
+        then: LispContinuation,
+    },
+    /// A `Try` step, ready to hand errors raised by anything above it on the stack to its catcher.
+    Catch {
+        form: LispCellRef,
+        catches: LispCatches,
+        catch: LispCatcher,
+    },
+    /// A `Handle` step, whose handler sees errors raised above it before they unwind.
+    Handler {
+        form: LispCellRef,
+        handle: LispCatcher,
+    },
+    /// A `Reset` step, marking how much of the stack a `Shift` captures.
+    Reset,
+    /// A handler running for `err`. Only the handlers below the `below`th frame see errors raised
+    /// while it runs, and if it returns the search for handlers carries on from there.
+    Handling {
+        below: usize,
+        err: LispError,
+        unwinds: bool,
+    },
+    /// A cleanup running while `err` unwinds, which raises `err` again when it's done.
+    Unwinding {
+        err: LispError,
+    },
+}
+
+/// The rest of an evaluation, captured by `call/cc` or `shift`. Frames are never changed once
+/// they're pushed, so a continuation can be resumed any number of times.
+pub struct Continuation {
+    frames: Captured,
+}
+
+enum Captured {
+    /// The whole stack, whose frames are shared with the stack it was captured from and any other
+    /// continuations captured from that
+    Undelimited(Spine),
+    /// The frames above a reset, which are copied onto the stack each time they're resumed
+    Delimited {
+        frames: Vec<Frame>,
+        /// The index of the first of the frames in the stack they were taken from
+        base: usize,
+    },
+}
+
+/// The machine's stack of frames. The frames pushed since the last `call/cc` are kept in `top`,
+/// above segments of frames frozen by earlier ones, so capturing the whole stack only freezes what
+/// was pushed since rather than copying all of it. Frames are moved straight out of a segment
+/// nothing else refers to, and otherwise copied out of it one at a time as they're returned to, so
+/// that the next capture doesn't freeze copies of frames that are already shared.
+struct Stack {
+    top: Vec<Frame>,
+    below: Spine,
+}
+
+/// The frames of a stack below some point: the first `len` frames of `segment`, then whatever's
+/// below that.
+#[derive(Clone)]
+struct Spine {
+    segment: Option<Rc<Segment>>,
+    len: usize,
+    /// The number of frames in all
+    depth: usize,
+}
+
+struct Segment {
+    frames: Vec<Frame>,
+    below: Spine,
+}
+
+impl Spine {
+    fn empty() -> Spine {
+        Spine {
+            segment: None,
+            len: 0,
+            depth: 0,
+        }
+    }
+}
+
+// Dropping a long chain of segments would otherwise recurse once per segment
+impl Drop for Segment {
+    fn drop(&mut self) {
+        let mut below = self.below.segment.take();
+
+        while let Some(segment) = below {
+            below = match Rc::try_unwrap(segment) {
+                Ok(mut segment) => segment.below.segment.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+impl Stack {
+    fn new() -> Stack {
+        Stack {
+            top: vec![],
+            below: Spine::empty(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.top.len() + self.below.depth
+    }
+
+    fn push(&mut self, frame: Frame) {
+        self.top.push(frame);
+    }
+
+    fn pop(&mut self) -> Option<Frame> {
+        if self.top.is_empty() {
+            self.refill();
+        }
+
+        self.top.pop()
+    }
+
+    fn last(&self) -> Option<&Frame> {
+        self.iter().next()
+    }
+
+    /// The frames from the top of the stack down.
+    fn iter(&self) -> Frames<'_> {
+        Frames {
+            top: self.top.iter().rev(),
+            below: &self.below,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.replace(Spine::empty());
+    }
+
+    /// Replaces the whole stack with the frames of `spine`.
+    fn replace(&mut self, spine: Spine) {
+        self.top.clear();
+        self.below = spine;
+    }
+
+    /// Freezes the frames in `top` into a new segment, and returns the whole stack for a
+    /// continuation to share.
+    fn share(&mut self) -> Spine {
+        if !self.top.is_empty() {
+            let frames = mem::take(&mut self.top);
+            let below = mem::replace(&mut self.below, Spine::empty());
+
+            count_allocations(frames.len());
+
+            self.below = Spine {
+                len: frames.len(),
+                depth: below.depth + frames.len(),
+                segment: Some(Rc::new(Segment {
+                    frames,
+                    below,
+                })),
+            };
+        }
+
+        self.below.clone()
+    }
+
+    /// Takes the frames from the `base`th up off the stack.
+    fn split_off(&mut self, base: usize) -> Vec<Frame> {
+        let mut chunks = vec![];
+
+        while self.len() > base {
+            if self.top.is_empty() {
+                self.refill();
+            }
+
+            let keep = self.top.len().saturating_sub(self.len() - base);
+            chunks.push(self.top.split_off(keep));
+        }
+
+        chunks.into_iter().rev().flatten().collect()
+    }
+
+    /// Moves the frames of the segment below `top` into it, or copies just the top one if the
+    /// segment is shared.
+    fn refill(&mut self) {
+        let segment = match self.below.segment.take() {
+            Some(segment) => segment,
+            None => return,
+        };
+        let len = self.below.len;
+
+        match Rc::try_unwrap(segment) {
+            Ok(mut segment) => {
+                self.top = mem::take(&mut segment.frames);
+                self.top.truncate(len);
+                self.below = mem::replace(&mut segment.below, Spine::empty());
+            }
+            Err(segment) => {
+                self.top.push(segment.frames[len - 1].clone());
+                count_allocation();
+
+                self.below = match len - 1 {
+                    0 => segment.below.clone(),
+                    _ => Spine {
+                        segment: Some(segment),
+                        len: len - 1,
+                        depth: self.below.depth - 1,
+                    },
+                };
+            }
+        }
+    }
+}
+
+struct Frames<'a> {
+    top: Rev<slice::Iter<'a, Frame>>,
+    below: &'a Spine,
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = &'a Frame;
+
+    fn next(&mut self) -> Option<&'a Frame> {
+        loop {
+            if let Some(frame) = self.top.next() {
+                return Some(frame);
+            }
+
+            let below = self.below;
+            let segment = below.segment.as_ref()?;
+
+            self.top = segment.frames[..below.len].iter().rev();
+            self.below = &segment.below;
+        }
+    }
+}
+
+/// Calls to a captured continuation, which resume it with their arg.
+struct ContinuationExecutorImpl {
+    continuation: Rc<Continuation>,
+}
+
+impl LispFuncExecutor for ContinuationExecutorImpl {
+    fn exec(&self, env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+        run_step(self.exec_step(env, args)?)
+    }
+
+    fn exec_step(&self, _env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+        match args {
+            [value] => Ok(LispStep::Resume(self.continuation.clone(), value.clone())),
+            _ => Err(LispError::arity("continuation", "1", args.len())),
+        }
+    }
+}
+
+/// An evaluator that keeps its continuation in a heap-allocated stack of frames rather than on the
+/// Rust stack, so nesting depth is bounded only by `ExecOptions::max_stack_depth`.
+struct Machine<'a> {
+    stack: Stack,
+    max_stack_depth: usize,
+    budget: Budget,
+    /// The program being run, if any, so errors can be given spans before they're caught.
+    program: Option<&'a LispProgram>,
+}
+
+impl<'a> Machine<'a> {
+    fn new(options: &ExecOptions) -> Machine<'a> {
+        Machine {
+            stack: Stack::new(),
+            max_stack_depth: options.max_stack_depth,
+            budget: Budget::new(options),
+            program: None,
+        }
+    }
+
+    fn run_prog(&mut self, env: Rc<RefCell<Environment>>, program: &'a LispProgram) -> LispResult {
+        let mut result = lisp_null();
+        self.program = Some(program);
+
+        for form in program.forms.iter() {
+            result = self.run(Control::Eval(env.clone(), form.clone())).map_err(|err| program.locate_error(err))?;
+        }
+
+        Ok(result)
+    }
+
+    fn run(&mut self, mut control: Control) -> LispResult {
+        loop {
+            let next = match control {
+                Control::Eval(env, cell) => match self.budget.charge_step() {
+                    Ok(()) => self.eval(env, cell),
+                    Err(err) => Err(err.with_form(&cell)),
+                },
+                Control::Return(value) => match self.stack.pop() {
+                    Some(frame) => self.resume(frame, value),
+                    None => return Ok(value),
+                },
+            };
+
+            control = match next {
+                Ok(next) => next,
+                Err(err) => self.raise(err)?,
+            };
+        }
+    }
+
+    /// Raises `err` where evaluation has got to. `handler-bind` handlers get to see it first, then
+    /// it unwinds the stack to whatever catches it.
+    fn raise(&mut self, err: LispError) -> Result<Control, LispError> {
+        // Handlers have already seen an error that's carrying on after a cleanup
+        let is_resumed = match self.stack.last() {
+            Some(Frame::Unwinding { err: unwinding }) => *unwinding == err,
+            _ => false,
+        };
+
+        if is_resumed {
+            self.stack.pop();
+
+            return self.unwind(err);
+        }
+
+        let missing_restart = match err.kind {
+            LispErrorKind::RestartInvoked { ref name, .. } if !self.has_restart(name) => Some(name.clone()),
+            _ => None,
+        };
+
+        if let Some(name) = missing_restart {
+            let no_restart = LispError::no_restart(&name);
+
+            return self.raise(match err.form {
+                Some(ref form) => no_restart.with_form(form),
+                None => no_restart,
+            });
+        }
+
+        match err.kind.is_catchable() {
+            true => {
+                let top = self.stack.len();
+
+                self.signal(err, top, true)
+            }
+            false => self.unwind(err),
+        }
+    }
+
+    /// Hands `err` to the innermost `handler-bind` handler below the `from`th frame, leaving a
+    /// frame to carry on with the ones further out if the handler returns. Errors go no further
+    /// than the innermost `try` that might catch them, and unwind once there are no handlers left,
+    /// while conditions from `signal` pass every `try` by and finish with nil.
+    fn signal(&mut self, err: LispError, from: usize, unwinds: bool) -> Result<Control, LispError> {
+        let mut index = from;
+        let mut found = None;
+        let mut frames = self.stack.iter().skip(self.stack.len() - from);
+
+        while let Some(frame) = frames.next() {
+            index -= 1;
+
+            match *frame {
+                // Handlers don't see errors raised by themselves or the handlers inside them
+                Frame::Handling { below, .. } => {
+                    for _ in below..index {
+                        frames.next();
+                    }
+
+                    index = below;
+                }
+                Frame::Catch {
+                    catches: LispCatches::Errors,
+                    ..
+                } if unwinds => break,
+                Frame::Handler { ref form, ref handle } => {
+                    found = Some((form.clone(), handle.clone()));
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        drop(frames);
+
+        let (form, handle) = match found {
+            Some(found) => found,
+            None if unwinds => return self.unwind(err),
+            None => return Ok(Control::Return(lisp_null())),
+        };
+
+        let err = self.locate(err);
+        let frame = Frame::Handling {
+            below: index,
+            err: err.clone(),
+            unwinds,
+        };
+        self.push(frame, &form)?;
+
+        let step = handle(err).map_err(|err| err.with_form(&form))?;
+
+        self.step(step, &form)
+    }
+
+    /// Pops frames until one catches `err`, and carries on with whatever its catcher returns. Errors
+    /// that can't be caught, or that nothing catches, empty the stack and end evaluation.
+    fn unwind(&mut self, mut err: LispError) -> Result<Control, LispError> {
+        let is_restart = matches!(err.kind, LispErrorKind::RestartInvoked { .. });
+
+        if !is_restart && !err.kind.is_catchable() {
+            self.stack.clear();
+
+            return Err(err);
+        }
+
+        loop {
+            let (form, catches, catch) = match self.stack.pop() {
+                Some(Frame::Catch { form, catches, catch }) => (form, catches, catch),
+                Some(_) => continue,
+                None => return Err(err),
+            };
+
+            if !catches.catches(&err.kind) {
+                continue;
+            }
+
+            let err_at = self.locate(err);
+
+            let step = match catch(err_at.clone()) {
+                Ok(step) => step,
+                // Handlers have already seen errors that only passed through a cleanup
+                Err(ref rethrown) if catches == LispCatches::Unwinding && *rethrown == err_at => {
+                    err = err_at;
+                    continue;
+                }
+                Err(rethrown) => return self.raise(rethrown.with_form(&form)),
+            };
+
+            if catches == LispCatches::Unwinding {
+                self.push(Frame::Unwinding { err: err_at }, &form)?;
+            }
+
+            return self.step(step, &form);
+        }
+    }
+
+    /// Wraps `frames` in a function that resumes them.
+    fn capture(frames: Captured) -> LispCellRef {
+        let continuation = Continuation {
+            frames,
+        };
+
+        let executor = ContinuationExecutorImpl {
+            continuation: Rc::new(continuation),
+        };
+
+        LispCell::Func(LispFunc::new("continuation".to_string(), LispFuncType::Normal, Box::new(executor))).to_ref()
+    }
+
+    /// Carries on from `continuation` with `value`. An undelimited continuation replaces the whole
+    /// stack, while a delimited one is pushed on top of it inside a new reset, so its value is
+    /// returned to whatever called it.
+    fn resume_continuation(
+        &mut self,
+        continuation: &Continuation,
+        value: LispCellRef,
+        form: &LispCellRef,
+    ) -> Result<Control, LispError> {
+        let (frames, captured_base) = match continuation.frames {
+            Captured::Undelimited(ref spine) => {
+                self.stack.replace(spine.clone());
+
+                return Ok(Control::Return(value));
+            }
+            Captured::Delimited { ref frames, base } => (frames, base),
+        };
+
+        self.push(Frame::Reset, form)?;
+
+        if self.stack.len() + frames.len() > self.max_stack_depth {
+            return Err(LispError::stack_depth_exceeded(self.max_stack_depth).with_form(form));
+        }
+
+        let base = self.stack.len();
+        count_allocations(frames.len());
+
+        for frame in frames.iter() {
+            let frame = match *frame {
+                // The handler a running handler's frame points at may have moved along with it
+                Frame::Handling {
+                    below,
+                    ref err,
+                    unwinds,
+                } if below >= captured_base => Frame::Handling {
+                    below: below - captured_base + base,
+                    err: err.clone(),
+                    unwinds,
+                },
+                ref frame => frame.clone(),
+            };
+
+            self.stack.push(frame);
+        }
+
+        Ok(Control::Return(value))
+    }
+
+    fn has_restart(&self, name: &str) -> bool {
+        self.stack.iter().any(|frame| match *frame {
+            Frame::Catch {
+                catches: LispCatches::Restarts(ref names),
+                ..
+            } => names.iter().any(|restart| restart == name),
+            _ => false,
+        })
+    }
+
+    fn locate(&self, err: LispError) -> LispError {
+        match self.program {
+            Some(program) => program.locate_error(err),
+            None => err,
+        }
+    }
+
+    fn push(&mut self, frame: Frame, form: &LispCellRef) -> Result<(), LispError> {
+        if self.stack.len() >= self.max_stack_depth {
+            return Err(LispError::stack_depth_exceeded(self.max_stack_depth).with_form(form));
+        }
+
+        self.stack.push(frame);
+
+        Ok(())
+    }
+
+    /// Turns the step returned by an op into what the machine should do next. `form` is the form
+    /// the op was applied for, which any errors raised later on by its continuations are pinned to.
+    fn step(&mut self, step: LispStep, form: &LispCellRef) -> Result<Control, LispError> {
+        match step {
+            LispStep::Done(value) => Ok(Control::Return(value)),
+            LispStep::TailCall(env, cell) => Ok(Control::Eval(env, cell)),
+            LispStep::EvalThen(env, cell, then) => {
+                self.push(
+                    Frame::Then {
+                        form: form.clone(),
+                        then,
+                    },
+                    form,
+                )?;
+
+                Ok(Control::Eval(env, cell))
+            }
+            LispStep::Try(body, catches, catch) => {
+                self.push(
+                    Frame::Catch {
+                        form: form.clone(),
+                        catches,
+                        catch,
+                    },
+                    form,
+                )?;
+
+                self.step(*body, form)
+            }
+            LispStep::Handle(body, handle) => {
+                self.push(
+                    Frame::Handler {
+                        form: form.clone(),
+                        handle,
+                    },
+                    form,
+                )?;
+
+                self.step(*body, form)
+            }
+            LispStep::Signal(condition) => {
+                let top = self.stack.len();
+
+                self.signal(condition.with_form(form), top, false)
+            }
+            LispStep::CallCC(receive) => {
+                let continuation = Machine::capture(Captured::Undelimited(self.stack.share()));
+                let step = receive(continuation).map_err(|err| err.with_form(form))?;
+
+                self.step(step, form)
+            }
+            LispStep::Reset(body) => {
+                self.push(Frame::Reset, form)?;
+
+                self.step(*body, form)
+            }
+            LispStep::Shift(receive) => {
+                let reset = self.stack.iter().position(|frame| matches!(*frame, Frame::Reset));
+
+                let base = match reset {
+                    Some(above) => self.stack.len() - above,
+                    None => return Err(LispError::syntax("shift used outside of a reset").with_form(form)),
+                };
+
+                // The reset stays on the stack, so the value of `receive`'s step is returned from it
+                let frames = self.stack.split_off(base);
+                count_allocations(frames.len());
+
+                let continuation = Machine::capture(Captured::Delimited {
+                    frames,
+                    base,
+                });
+
+                let step = receive(continuation).map_err(|err| err.with_form(form))?;
+
+                self.step(step, form)
+            }
+            LispStep::Resume(continuation, value) => self.resume_continuation(&continuation, value, form),
+            LispStep::Then(body, then) => {
+                self.push(
+                    Frame::Then {
+                        form: form.clone(),
+                        then,
+                    },
+                    form,
+                )?;
+
+                self.step(*body, form)
+            }
+        }
+    }
+
+    fn eval(&mut self, env: Rc<RefCell<Environment>>, cell: LispCellRef) -> Result<Control, LispError> {
+        match *cell.borrow() {
+            // Keywords like `:name` evaluate to themselves, so they can be passed to keyword parameters
+            LispCell::Atom(ref symbol) if symbol.len() > 1 && symbol.starts_with(':') => {
+                Ok(Control::Return(cell.clone()))
+            }
+            LispCell::Atom(ref symbol) => {
+                let maybe_sym = env.borrow().find_sym(symbol);
+
+                match maybe_sym {
+                    Some(sym) => Ok(Control::Return(sym.clone())),
+                    None => Err(LispError::unbound_symbol(symbol).with_form(&cell)),
+                }
+            }
+            LispCell::Quoted(ref quoted) => {
+                log(|| println!("Unquoting {:?}", quoted));
+
+                Ok(Control::Return(quoted.clone()))
+            }
+            LispCell::Quasiquoted(ref template) => {
+                let step = exec_quasiquoted(env, template.clone()).map_err(|err| err.with_form(&cell))?;
+
+                self.step(step, &cell)
+            }
+            LispCell::Unquoted(_) | LispCell::UnquoteSpliced(_) => {
+                Err(LispError::syntax("unquote used outside of a quasiquote").with_form(&cell))
+            }
+            LispCell::Str(_)
+            | LispCell::Number(_)
+            | LispCell::Bool(_)
+            | LispCell::Func(_)
+            | LispCell::Error(_) => Ok(Control::Return(cell.clone())),
+            LispCell::List(ref list) => {
+                let (x, xs) = LispList::split(list.clone());
+
+                let head = match x.borrow().get_value() {
+                    Some(head) => head,
+                    // The empty list is nil, which evaluates to itself
+                    None => return Ok(Control::Return(cell.clone())),
+                };
+
+                let frame = Frame::Call {
+                    env: env.clone(),
+                    form: cell.clone(),
+                    function: None,
+                    args: xs,
+                    values: vec![],
+                };
+                self.push(frame, &cell)?;
+
+                Ok(Control::Eval(env, head))
+            }
+        }
+    }
+
+    fn resume(&mut self, frame: Frame, value: LispCellRef) -> Result<Control, LispError> {
+        match frame {
+            Frame::Then { form, then } => {
+                let step = then(value).map_err(|err| err.with_form(&form))?;
+
+                self.step(step, &form)
+            }
+            // Nothing went wrong, so the try's value is its body's
+            Frame::Catch { .. } | Frame::Handler { .. } | Frame::Reset => Ok(Control::Return(value)),
+            // The handler declined to deal with the error, so try the next one out
+            Frame::Handling { below, err, unwinds } => self.signal(err, below, unwinds),
+            Frame::Unwinding { .. } => Ok(Control::Return(value)),
+            Frame::Call {
+                env,
+                form,
+                function: None,
+                args,
+                values,
+            } => {
+                let func_type = match *value.borrow() {
+                    LispCell::Func(ref function) => function.func_type.clone(),
+                    _ => return Err(LispError::not_a_function(&value).with_form(&form)),
+                };
+
+                match func_type {
+                    LispFuncType::Normal => self.next_arg(env, form, value, args, values),
+                    _ => {
+                        let args = match args {
+                            Some(args) => LispList::to_vec(args),
+                            None => vec![],
+                        };
+
+                        self.apply(env, form, value, args)
+                    }
+                }
+            }
+            Frame::Call {
+                env,
+                form,
+                function: Some(function),
+                args,
+                mut values,
+            } => {
+                values.push(value);
+
+                self.next_arg(env, form, function, args, values)
+            }
+        }
+    }
+
+    /// Evaluates the next of a normal function's args, or calls it once they've all been evaluated.
+    fn next_arg(
+        &mut self,
+        env: Rc<RefCell<Environment>>,
+        form: LispCellRef,
+        function: LispCellRef,
+        args: Option<LispListRef>,
+        values: Vec<LispCellRef>,
+    ) -> Result<Control, LispError> {
+        let (arg, args) = match args {
+            Some(args) => {
+                let (node, rest) = LispList::split(args);
+                let arg = node.borrow().get_value();
+
+                (arg, rest)
+            }
+            None => (None, None),
+        };
+
+        let arg = match arg {
+            Some(arg) => arg,
+            None => return self.apply(env, form, function, values),
+        };
+
+        let frame = Frame::Call {
+            env: env.clone(),
+            form: form.clone(),
+            function: Some(function),
+            args,
+            values,
+        };
+        self.push(frame, &form)?;
+
+        Ok(Control::Eval(env, arg))
+    }
+
+    fn apply(
+        &mut self,
+        env: Rc<RefCell<Environment>>,
+        form: LispCellRef,
+        function_cell: LispCellRef,
+        args: Vec<LispCellRef>,
+    ) -> Result<Control, LispError> {
+        let step = match *function_cell.borrow() {
+            LispCell::Func(ref function) => {
+                let step = function.func_executor.exec_step(env.clone(), &args).map_err(|err| err.with_form(&form))?;
+
+                match function.func_type {
+                    // A macro's result is an expansion to evaluate in place of the call
+                    LispFuncType::Macro => step.and_then(move |expansion| {
+                        log(|| println!("Expanded macro to {:?}", expansion));
+
+                        Ok(LispStep::TailCall(env.clone(), expansion))
+                    })?,
+                    _ => step,
+                }
+            }
+            _ => return Err(LispError::not_a_function(&function_cell).with_form(&form)),
+        };
+
+        self.step(step, &form)
+    }
 }
 
-pub fn exec_ref(env: Rc<RefCell<Environment>>, cell_ref: &LispCellRef) -> LispCellRef {
-    exec_rec(env, cell_ref.clone())
+/// Tracks how much of the step, cell and time limits from `ExecOptions` evaluation has used up.
+struct Budget {
+    steps: u64,
+    max_steps: Option<u64>,
+    cells_at_start: usize,
+    max_cells: Option<usize>,
+    started_at: Instant,
+    timeout: Option<Duration>,
 }
 
-fn exec_rec(env: Rc<RefCell<Environment>>, cell: LispCellRef) -> LispCellRef {
-    match *cell.borrow() {
-        LispCell::Atom(ref symbol) => {
-            let maybe_sym = env.borrow().find_sym(symbol);
+impl Budget {
+    fn new(options: &ExecOptions) -> Budget {
+        Budget {
+            steps: 0,
+            max_steps: options.max_steps,
+            cells_at_start: allocated_cells(),
+            max_cells: options.max_cells,
+            started_at: Instant::now(),
+            timeout: options.timeout,
+        }
+    }
+
+    fn charge_step(&mut self) -> Result<(), LispError> {
+        self.steps += 1;
+
+        if let Some(max_steps) = self.max_steps {
+            if self.steps > max_steps {
+                return Err(LispError::step_limit_exceeded(max_steps));
+            }
+        }
+
+        self.check_cells()?;
 
-            match maybe_sym {
-                Some(sym) => sym.clone(),
-                None => panic!("No symbol found with name {}", symbol),
+        if let Some(timeout) = self.timeout {
+            if self.steps.is_multiple_of(TIMEOUT_CHECK_INTERVAL) && self.started_at.elapsed() > timeout {
+                return Err(LispError::timeout(timeout));
             }
         }
-        LispCell::Quoted(ref quoted) => {
-            log(|| println!("Unquoting {:?}", quoted));
 
-            quoted.clone()
+        Ok(())
+    }
+
+    fn check_cells(&self) -> Result<(), LispError> {
+        if let Some(max_cells) = self.max_cells {
+            if allocated_cells() - self.cells_at_start > max_cells {
+                return Err(LispError::cell_limit_exceeded(max_cells));
+            }
         }
-        LispCell::Str(_) | LispCell::Number(_) => cell.clone(),
+
+        Ok(())
+    }
+}
+
+/// Expands `form` once if it's a call to a macro, otherwise returns `None`.
+///
+/// Only calls whose head is a symbol bound to a macro in `env` are expanded; the expansion itself
+/// isn't evaluated.
+pub fn macroexpand_1(env: Rc<RefCell<Environment>>, form: &LispCellRef) -> Result<Option<LispStep>, LispError> {
+    let (head, args) = match *form.borrow() {
         LispCell::List(ref list) => {
             let (x, xs) = LispList::split(list.clone());
-
-            let function = exec_rec(env.clone(), x.borrow().get_value().expect("There should be a value in the head of the args list to exec"));
             let args = match xs {
-                Some(cells) => LispList::to_vec(cells).iter().map(|cell| cell.clone()).collect::<Vec<LispCellRef>>(),
-                None => vec![]
-            } ;
+                Some(cells) => LispList::to_vec(cells),
+                None => vec![],
+            };
 
-            call_fn(env.clone(), function, &args)
+            let head = x.borrow().get_value();
+            match head {
+                Some(head) => (head, args),
+                None => return Ok(None),
+            }
         }
-        ref t @ _ => panic!("LispCell type {:?} not implemented in exec!", t),
+        _ => return Ok(None),
+    };
+
+    let symbol = match *head.borrow() {
+        LispCell::Atom(ref symbol) => symbol.clone(),
+        _ => return Ok(None),
+    };
+
+    let function = match env.borrow().find_sym(&symbol) {
+        Some(function) => function,
+        None => return Ok(None),
+    };
+
+    let function = function.borrow();
+    match *function {
+        LispCell::Func(ref function) if function.func_type == LispFuncType::Macro => {
+            function.func_executor.exec_step(env.clone(), &args).map(Some).map_err(|err| err.with_form(form))
+        }
+        _ => Ok(None),
     }
 }
 
-fn call_fn(env: Rc<RefCell<Environment>>, function_cell: LispCellRef, args: &[LispCellRef]) -> LispCellRef {
-    match *function_cell.borrow() {
-        LispCell::Func(ref function) => {
-            let args = match function.func_type {
-                LispFuncType::Macro | LispFuncType::SpecialForm => args.to_vec(),
-                _ => args.clone().iter().map(|cell| exec_rec(env.clone(), cell.clone())).collect(),
-            };
+/// Repeatedly expands `form` until its head is no longer a macro.
+pub fn macroexpand(env: Rc<RefCell<Environment>>, form: LispCellRef) -> Result<LispStep, LispError> {
+    match macroexpand_1(env.clone(), &form)? {
+        Some(step) => step.and_then(move |expansion| macroexpand(env.clone(), expansion)),
+        None => Ok(LispStep::Done(form)),
+    }
+}
+
+/// Evaluates a quasiquoted template by evaluating its unquoted holes (in order) and then building a
+/// copy of the template with the results filled in.
+///
+/// Holes only belong to this quasiquote if they're nested in as many unquotes as quasiquotes, so
+/// a nested quasiquote's unquotes are copied over as-is apart from any holes within them.
+fn exec_quasiquoted(env: Rc<RefCell<Environment>>, template: LispCellRef) -> Result<LispStep, LispError> {
+    let holes = collect_holes(&template)?;
+
+    eval_each(env, holes, move |values| fill_template(&template, &mut values.into_iter()).map(LispStep::Done))
+}
 
-            function.func_executor.exec(env.clone(), &args)
+/// The expressions in `template`'s holes, in the order they appear.
+fn collect_holes(template: &LispCellRef) -> Result<Vec<LispCellRef>, LispError> {
+    let mut holes = vec![];
+
+    // Cells still to look through, along with how many quasiquotes deep they are and whether
+    // they're directly inside a list
+    let mut pending = vec![(template.clone(), 1, false)];
+
+    while let Some((cell, depth, in_list)) = pending.pop() {
+        match *cell.borrow() {
+            LispCell::Unquoted(ref inner) if depth == 1 => holes.push(inner.clone()),
+            LispCell::UnquoteSpliced(ref inner) if depth == 1 => {
+                if !in_list {
+                    return Err(LispError::syntax("unquote-splicing used outside of a list").with_form(&cell));
+                }
+
+                holes.push(inner.clone())
+            }
+            LispCell::Unquoted(ref inner) | LispCell::UnquoteSpliced(ref inner) => {
+                pending.push((inner.clone(), depth - 1, false))
+            }
+            LispCell::Quasiquoted(ref inner) => pending.push((inner.clone(), depth + 1, false)),
+            LispCell::Quoted(ref inner) => pending.push((inner.clone(), depth, false)),
+            LispCell::List(ref list) => {
+                // Pushed last to first, so they're looked through in order
+                for cell in LispList::to_vec(list.clone()).into_iter().rev() {
+                    pending.push((cell, depth, true));
+                }
+            }
+            _ => {}
         }
-        ref t @ _ => panic!("LispCell type {:?} not a Func!", t),
     }
+
+    Ok(holes)
+}
+
+/// What's left for `fill_template` to do.
+enum Fill {
+    /// Copy a cell that's this many quasiquotes deep, filling in its holes.
+    Cell(LispCellRef, usize),
+    /// Splice the next value into the list being built, for the `,@` hole given.
+    Splice(LispCellRef),
+    /// Wrap the last cell copied back up in the quote it came out of.
+    Wrap(fn(LispCellRef) -> LispCell),
+    /// Finish the list being built.
+    FinishList,
+}
+
+/// Builds a copy of `template` with `values` filled into its holes, in the order `collect_holes`
+/// found them.
+fn fill_template<I>(template: &LispCellRef, values: &mut I) -> LispResult
+where
+    I: Iterator<Item = LispCellRef>,
+{
+    // Copies are built on an explicit stack rather than by recursing, so deeply nested templates
+    // can't overflow the Rust stack. Each finished cell goes on the end of the innermost list being
+    // built, with the bottom one collecting the result.
+    let mut pending = vec![Fill::Cell(template.clone(), 1)];
+    let mut lists = vec![vec![]];
+
+    while let Some(next) = pending.pop() {
+        let filled = match next {
+            Fill::Cell(cell, depth) => match *cell.borrow() {
+                LispCell::Unquoted(_) if depth == 1 => values.next().expect("a value for each hole"),
+                LispCell::Unquoted(ref inner) => {
+                    pending.push(Fill::Wrap(LispCell::Unquoted));
+                    pending.push(Fill::Cell(inner.clone(), depth - 1));
+                    continue;
+                }
+                LispCell::UnquoteSpliced(ref inner) => {
+                    pending.push(Fill::Wrap(LispCell::UnquoteSpliced));
+                    pending.push(Fill::Cell(inner.clone(), depth - 1));
+                    continue;
+                }
+                LispCell::Quasiquoted(ref inner) => {
+                    pending.push(Fill::Wrap(LispCell::Quasiquoted));
+                    pending.push(Fill::Cell(inner.clone(), depth + 1));
+                    continue;
+                }
+                LispCell::Quoted(ref inner) => {
+                    pending.push(Fill::Wrap(LispCell::Quoted));
+                    pending.push(Fill::Cell(inner.clone(), depth));
+                    continue;
+                }
+                LispCell::List(ref list) => {
+                    lists.push(vec![]);
+                    pending.push(Fill::FinishList);
+
+                    // Pushed last to first, so they're filled in order
+                    for cell in LispList::to_vec(list.clone()).into_iter().rev() {
+                        let is_splice = match *cell.borrow() {
+                            LispCell::UnquoteSpliced(_) => depth == 1,
+                            _ => false,
+                        };
+
+                        match is_splice {
+                            true => pending.push(Fill::Splice(cell)),
+                            false => pending.push(Fill::Cell(cell, depth)),
+                        }
+                    }
+
+                    continue;
+                }
+                _ => cell.clone(),
+            },
+            Fill::Splice(hole) => {
+                let spliced = values.next().expect("a value for each hole");
+                let spliced_list = match *spliced.borrow() {
+                    LispCell::List(ref spliced_list) => spliced_list.clone(),
+                    _ => return Err(LispError::type_error("list to splice", &spliced).with_form(&hole)),
+                };
+
+                let filled_list = lists.last_mut().expect("a list to splice into");
+                filled_list.extend(LispList::to_vec(spliced_list));
+
+                continue;
+            }
+            Fill::Wrap(wrap) => {
+                let inner = lists.last_mut().and_then(|filled_list| filled_list.pop()).expect("a cell to wrap");
+
+                wrap(inner).to_ref()
+            }
+            Fill::FinishList => {
+                let filled_list = lists.pop().expect("a list to finish");
+
+                LispCell::List(LispList::from_vec(filled_list).to_ref()).to_ref()
+            }
+        };
+
+        lists.last_mut().expect("a list to add to").push(filled);
+    }
+
+    Ok(lists.pop().and_then(|mut filled| filled.pop()).expect("a filled in template"))
 }
@@ -14,19 +14,27 @@ pub use util::*;
 #[cfg(test)]
 mod test {
     use std::cell::RefCell;
+    use std::io::Cursor;
     use std::rc::Rc;
+    use std::time::Duration;
 
     use print::print_cell;
 
-    use super::core::{Environment, LispCellRef, LispProgram};
-    use super::{exec_prog, parse, print};
+    use super::core::{
+        allocated_cells, Environment, LispCell, LispCellRef, LispCommentKind, LispError, LispErrorKind, LispList,
+        LispProgram, LispResult, SourceMap, Span, MAX_EXACT_BITS,
+    };
+    use super::{
+        exec_prog, exec_prog_with_options, parse, parse_and_exec_with_options, parse_with_options, print, read_forms,
+        ExecOptions, ParseOptions, Reader,
+    };
 
     use super::util::*;
 
     #[test]
     fn basic_parsing_and_printing() {
         let program_str = "(do (print (+ 1 2) (- 1 2)) (foo bar baz (qux (+ 1 2) (blah) blah)))";
-        let program = parse(program_str.to_string());
+        let program = parse(program_str.to_string()).unwrap();
 
         assert_eq!(print(&program), program_str, "Expected program_str and printed program to be equal");
     }
@@ -34,18 +42,20 @@ mod test {
     #[test]
     fn basic_parsing() {
         let program_str = "(print (concat (+ 1 2) (- 3 5)))";
-        let parsed_program = parse(program_str.to_string());
+        let parsed_program = parse(program_str.to_string()).unwrap();
 
         let expected_program = LispProgram {
             text: program_str.to_string(),
-            entry: Some(make_list(vec![
+            spans: SourceMap::new(),
+            comments: vec![],
+            forms: vec![make_list(vec![
                 make_atom("print"),
                 make_list(vec![
                     make_atom("concat"),
-                    make_list(vec![make_atom("+"), make_num(1f32), make_num(2f32)]),
-                    make_list(vec![make_atom("-"), make_num(3f32), make_num(5f32)]),
+                    make_list(vec![make_atom("+"), make_int(1), make_int(2)]),
+                    make_list(vec![make_atom("-"), make_int(3), make_int(5)]),
                 ]),
-            ])),
+            ])],
         };
 
         assert_eq!(parsed_program, expected_program, "Expected parsed program and expected program to be equal")
@@ -54,19 +64,21 @@ mod test {
     #[test]
     fn basic_list_parsing() {
         let program_str = "(print (+ 1 2) '(1 (+ 1 2)) (- 3 5))";
-        let parsed_program = parse(program_str.to_string());
+        let parsed_program = parse(program_str.to_string()).unwrap();
 
         let expected_program = LispProgram {
             text: program_str.to_string(),
-            entry: Some(make_list(vec![
+            spans: SourceMap::new(),
+            comments: vec![],
+            forms: vec![make_list(vec![
                 make_atom("print"),
-                make_list(vec![make_atom("+"), make_num(1f32), make_num(2f32)]),
+                make_list(vec![make_atom("+"), make_int(1), make_int(2)]),
                 make_quoted(make_list(vec![
-                    make_num(1f32),
-                    make_list(vec![make_atom("+"), make_num(1f32), make_num(2f32)]),
+                    make_int(1),
+                    make_list(vec![make_atom("+"), make_int(1), make_int(2)]),
                 ])),
-                make_list(vec![make_atom("-"), make_num(3f32), make_num(5f32)]),
-            ])),
+                make_list(vec![make_atom("-"), make_int(3), make_int(5)]),
+            ])],
         };
 
         assert_eq!(parsed_program, expected_program, "Expected parsed program and expected program to be equal")
@@ -75,39 +87,296 @@ mod test {
     #[test]
     fn parse_empty_list() {
         let program_str = "(print ())";
-        let parsed_program = parse(program_str.to_string());
+        let parsed_program = parse(program_str.to_string()).unwrap();
 
         let expected_program = LispProgram {
             text: program_str.to_string(),
-            entry: Some(make_list(vec![make_atom("print"), make_list(vec![])])),
+            spans: SourceMap::new(),
+            comments: vec![],
+            forms: vec![make_list(vec![make_atom("print"), make_list(vec![])])],
         };
 
         assert_eq!(parsed_program, expected_program, "Expected parsed program and expected program to be equal")
     }
 
+    #[test]
+    fn parse_multiple_forms() {
+        let program_str = "(def x 1)\n(print x)\n'foo";
+        let parsed_program = parse(program_str.to_string()).unwrap();
+
+        let expected_forms = vec![
+            make_list(vec![make_atom("def"), make_atom("x"), make_int(1)]),
+            make_list(vec![make_atom("print"), make_atom("x")]),
+            make_quoted(make_atom("foo")),
+        ];
+
+        assert_eq!(parsed_program.forms, expected_forms);
+        assert_eq!(print(&parsed_program), program_str);
+    }
+
+    #[test]
+    fn parse_empty_program() {
+        let parsed_program = parse("  \n ".to_string()).unwrap();
+
+        assert_eq!(parsed_program.forms, vec![]);
+        assert_eq!(print(&parsed_program), "");
+    }
+
+    #[test]
+    fn parse_comments() {
+        let program_str = "; leading\n(+ 1 #| block #| nested |# |# 2 #;(ignored (form)) 3) ; trailing\n#;4 (list)";
+        let parsed_program = parse(program_str.to_string()).unwrap();
+
+        let expected_forms = vec![
+            make_list(vec![make_atom("+"), make_int(1), make_int(2), make_int(3)]),
+            make_list(vec![make_atom("list")]),
+        ];
+
+        assert_eq!(parsed_program.forms, expected_forms);
+        assert_eq!(parsed_program.comments, vec![]);
+    }
+
+    #[test]
+    fn parse_and_print_preserved_comments() {
+        let program_str = "; header\n(def x 1) ; x\n#| about\n   foo |#\n(defn foo () (+ x #;2 1))\n#;(foo)";
+        let options = ParseOptions {
+            keep_comments: true,
+        };
+        let parsed_program = parse_with_options(program_str.to_string(), options).unwrap();
+
+        let comment_texts: Vec<&str> = parsed_program.comments.iter().map(|comment| comment.text.as_str()).collect();
+        assert_eq!(comment_texts, vec!["; header", "; x", "#| about\n   foo |#", "#;2", "#;(foo)"]);
+        assert_eq!(parsed_program.comments[2].kind, LispCommentKind::Block);
+        assert_eq!(parsed_program.comments[2].span.line, 3);
+
+        assert_eq!(
+            print(&parsed_program),
+            "; header\n(def x 1)\n; x\n#| about\n   foo |#\n(defn foo () (+ x 1))\n#;(foo)"
+        );
+    }
+
+    #[test]
+    fn comments_end_words() {
+        let program_str = "(+ 1 2;c\n) (x;c\n) (a#|c|#b) (a#;b c) a#b";
+        let parsed_program = parse(program_str.to_string()).unwrap();
+
+        let expected_forms = vec![
+            make_list(vec![make_atom("+"), make_int(1), make_int(2)]),
+            make_list(vec![make_atom("x")]),
+            make_list(vec![make_atom("a"), make_atom("b")]),
+            make_list(vec![make_atom("a"), make_atom("c")]),
+            make_atom("a#b"),
+        ];
+
+        assert_eq!(parsed_program.forms, expected_forms);
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        run_parse_error_test("(+ 1 #| 2 #| 3 |# 4)", "Invalid program: unterminated block comment", 5, 20);
+    }
+
+    #[test]
+    fn parse_strings_with_escapes() {
+        let program_str = r#"(print "hello, world" "say \"hi\"\n\tC:\\dir" "\u{1F600}\u{e9}")"#;
+        let parsed_program = parse(program_str.to_string()).unwrap();
+
+        let expected_forms = vec![make_list(vec![
+            make_atom("print"),
+            make_str("hello, world"),
+            make_str("say \"hi\"\n\tC:\\dir"),
+            make_str("\u{1F600}\u{e9}"),
+        ])];
+
+        assert_eq!(parsed_program.forms, expected_forms);
+        assert_eq!(print(&parsed_program), r#"(print "hello, world" "say \"hi\"\n\tC:\\dir" "😀é")"#);
+    }
+
+    #[test]
+    fn print_and_reparse_strings() {
+        let original = make_str("quote \" backslash \\ newline \n tab \t bell \u{7}");
+        let printed = print_cell(original.clone());
+
+        assert_eq!(printed, r#""quote \" backslash \\ newline \n tab \t bell \u{7}""#);
+        assert_eq!(parse(printed).unwrap().forms, vec![original]);
+    }
+
+    #[test]
+    fn invalid_string_escapes() {
+        run_parse_error_test(r#"(print "\q")"#, "Invalid program: unknown escape sequence", 8, 10);
+        run_parse_error_test(r#"(print "\u{110000}")"#, "Invalid program: invalid unicode code point", 8, 18);
+        run_parse_error_test(r#"(print "\u{}")"#, "Invalid program: malformed unicode escape", 8, 12);
+    }
+
+    #[test]
+    fn parse_deeply_nested_program() {
+        let depth = 50_000;
+        let program_str = format!("{}1{}", "('(".repeat(depth), "))".repeat(depth));
+        let parsed_program = parse(program_str).unwrap();
+
+        // Each level is `('( ... ))`: a list holding a quoted list holding the next level
+        let mut cell = parsed_program.forms[0].clone();
+        for _ in 0..depth * 2 {
+            let inner = match *cell.borrow() {
+                LispCell::List(ref list) => LispList::to_vec(list.clone()).pop().unwrap(),
+                LispCell::Quoted(ref quoted) => match *quoted.borrow() {
+                    LispCell::List(ref list) => LispList::to_vec(list.clone()).pop().unwrap(),
+                    _ => panic!("Expected a quoted list"),
+                },
+                _ => panic!("Expected a list or quoted cell"),
+            };
+            cell = inner;
+        }
+
+        assert_eq!(cell, make_int(1));
+    }
+
+    #[test]
+    fn parse_large_program() {
+        let row = "(\"row\" 1 2.5 foo) ; comment\n";
+        let program_str = format!("(data\n{})", row.repeat(20_000));
+        let parsed_program = parse(program_str).unwrap();
+
+        let rows = match *parsed_program.forms[0].borrow() {
+            LispCell::List(ref list) => LispList::to_vec(list.clone()),
+            _ => panic!("Expected a list"),
+        };
+
+        assert_eq!(rows.len(), 20_001);
+        assert_eq!(parsed_program.span_of(&rows[20_000]).map(|span| span.line), Some(20_001));
+    }
+
+    #[test]
+    fn read_forms_across_chunks() {
+        let mut reader = Reader::new();
+
+        reader.feed("(+ 1\n");
+        assert_eq!(reader.next_form().unwrap(), None);
+        assert!(reader.is_mid_form());
+
+        reader.feed("2)\n(def");
+        let program = reader.next_form().unwrap().unwrap();
+        assert_eq!(program.text, "(+ 1\n2)");
+        assert_eq!(program.forms, vec![make_list(vec![make_atom("+"), make_int(1), make_int(2)])]);
+        assert_eq!(reader.next_form().unwrap(), None);
+
+        reader.feed(" x \"a b");
+        assert_eq!(reader.next_form().unwrap(), None);
+
+        reader.feed("\") 'fo");
+        let program = reader.next_form().unwrap().unwrap();
+        assert_eq!(program.forms, vec![make_list(vec![make_atom("def"), make_atom("x"), make_str("a b")])]);
+        assert_eq!(program.span_of(&program.forms[0]), Some(Span { start: 0, end: 13, line: 3, col: 1 }));
+
+        // A word at the end of the input could still be continued by the next chunk
+        reader.feed("o");
+        assert_eq!(reader.next_form().unwrap(), None);
+        assert!(!reader.is_finished());
+
+        reader.finish();
+        let program = reader.next_form().unwrap().unwrap();
+        assert_eq!(program.forms, vec![make_quoted(make_atom("foo"))]);
+        assert_eq!(reader.next_form().unwrap(), None);
+        assert!(reader.is_finished());
+    }
+
+    #[test]
+    fn reader_reports_incomplete_and_hard_errors() {
+        let mut reader = Reader::new();
+
+        reader.feed("(foo #| unfinished");
+        assert_eq!(reader.next_form().unwrap(), None);
+        reader.feed(" comment |# \"\\u{4");
+        assert_eq!(reader.next_form().unwrap(), None);
+        reader.feed("1}\" ; trailing");
+        assert_eq!(reader.next_form().unwrap(), None);
+        reader.feed("\n)");
+        let program = reader.next_form().unwrap().unwrap();
+        assert_eq!(program.forms, vec![make_list(vec![make_atom("foo"), make_str("A")])]);
+
+        reader.feed("\n(bar))\n");
+        let program = reader.next_form().unwrap().unwrap();
+        assert_eq!(program.forms, vec![make_list(vec![make_atom("bar")])]);
+
+        let err = reader.next_form().unwrap_err();
+        assert_eq!(err.kind, LispErrorKind::Parse("Invalid program: unmatched parens".to_string()));
+        assert_eq!(err.span.map(|span| (span.line, span.col)), Some((3, 6)));
+        assert_eq!(
+            reader.render_error(&err),
+            "parse error: Invalid program: unmatched parens (line 3, col 6)\n  |\n3 | (bar))\n  |      ^"
+        );
+
+        // The reader picks back up with the next input after an error
+        reader.feed("(baz)");
+        reader.finish();
+        let program = reader.next_form().unwrap().unwrap();
+        assert_eq!(program.forms, vec![make_list(vec![make_atom("baz")])]);
+        assert_eq!(program.span_of(&program.forms[0]).map(|span| span.line), Some(4));
+
+        let mut reader = Reader::new();
+        reader.feed("(unclosed");
+        reader.finish();
+        let err = reader.next_form().unwrap_err();
+        assert_eq!(err.kind, LispErrorKind::Parse("Invalid program: unclosed parens".to_string()));
+    }
+
+    #[test]
+    fn read_long_multiline_comments_and_strings() {
+        let mut input = String::from("#| start\n");
+        for _ in 0..80_000 {
+            input.push_str("a line of a block comment #| nested |#\n");
+        }
+        input.push_str("|# (list \"");
+        for _ in 0..80_000 {
+            input.push_str("a line of a string\n");
+        }
+        input.push_str("\" 1)\n");
+
+        let programs: Vec<LispProgram> = read_forms(Cursor::new(input)).map(|program| program.unwrap()).collect();
+
+        assert_eq!(programs.len(), 1);
+        assert_eq!(
+            programs[0].forms,
+            vec![make_list(vec![make_atom("list"), make_str(&"a line of a string\n".repeat(80_000)), make_int(1)])]
+        );
+        assert_eq!(programs[0].span_of(&programs[0].forms[0]).map(|span| span.line), Some(80_002));
+    }
+
+    #[test]
+    fn read_and_exec_forms_lazily() {
+        let input = "(def x 2)\n(defn sq (y)\n  (* y y))\n; done\n(sq x) (sq 3)\n";
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        let results: Vec<String> = read_forms(Cursor::new(input))
+            .map(|program| print_cell(exec_prog(env.clone(), program.unwrap()).unwrap()))
+            .collect();
+
+        assert_eq!(results, vec!["x", "#sq", "4", "9"]);
+    }
+
     #[test]
     fn basic_adding() {
-        run_exec_test("(+ 1 2)", make_num(3f32))
+        run_exec_test("(+ 1 2)", make_int(3))
     }
 
     #[test]
     fn basic_adding_2() {
-        run_exec_test("(+ (+ 1 2) (+ 2 2))", make_num(7f32))
+        run_exec_test("(+ (+ 1 2) (+ 2 2))", make_int(7))
     }
 
     #[test]
     fn basic_math() {
-        run_exec_test("(* (+ (* 1 2 3) (- 2 2 -5) (+ 1 1 2 3) (/ 1 2)) (+ 1 5 6))", make_num(222f32))
+        run_exec_test("(* (+ (* 1 2 3) (- 2 2 -5) (+ 1 1 2 3) (/ 1 2)) (+ 1 5 6))", make_int(222))
     }
 
     #[test]
     fn basic_list() {
-        run_exec_test("(list 1 2 3)", make_list(vec![make_num(1f32), make_num(2f32), make_num(3f32)]))
+        run_exec_test("(list 1 2 3)", make_list(vec![make_int(1), make_int(2), make_int(3)]))
     }
 
     #[test]
     fn basic_def_and_do() {
-        run_exec_test("(do (def x (+ 2 2)) (+ x 5))", make_num(9f32))
+        run_exec_test("(do (def x (+ 2 2)) (+ x 5))", make_int(9))
     }
 
     #[test]
@@ -162,23 +431,1200 @@ mod test {
 
     #[test]
     fn original_var_is_unaltered_by_shadow() {
-        run_exec_test_literal("(do (def x 3) (def f (lambda (x) (* x 2))) (+ (f 12) x)", "27")
+        run_exec_test_literal("(do (def x 3) (def f (lambda (x) (* x 2))) (+ (f 12) x))", "27")
+    }
+
+    #[test]
+    fn multiple_top_level_forms() {
+        run_exec_test_literal("(def x 1) (defn inc (y) (+ y 1)) (inc (+ x 2))", "4")
+    }
+
+    #[test]
+    fn top_level_error_stops_execution() {
+        let program = parse("(def x 1) (+ y 1) (def x 2)".to_string()).unwrap();
+
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let err = exec_prog(env.clone(), program).unwrap_err();
+
+        assert_eq!(err.kind, LispErrorKind::UnboundSymbol("y".to_string()));
+        assert_eq!(env.borrow().find_sym(&"x".to_string()), Some(make_int(1)));
+    }
+
+    #[test]
+    fn unbound_symbol_error() {
+        run_exec_error_test("(+ x 1)", LispError::unbound_symbol("x"))
+    }
+
+    #[test]
+    fn arity_error() {
+        run_exec_error_test("(do (defn foo (x) x) (foo 1 2))", LispError::arity("foo", "1 for (foo x)", 2))
+    }
+
+    #[test]
+    fn type_error() {
+        run_exec_error_test("(+ 1 (list 2))", LispError::type_error("number", &make_list(vec![make_int(2)])))
+    }
+
+    #[test]
+    fn not_a_function_error() {
+        run_exec_error_test("(1 2)", LispError::not_a_function(&make_int(1)))
+    }
+
+    #[test]
+    fn parse_errors() {
+        run_parse_error_test("(+ 1 2))", "Invalid program: unmatched parens", 7, 8);
+        run_parse_error_test("(+ 1 2", "Invalid program: unclosed parens", 0, 1);
+        run_parse_error_test("(print \"foo)", "Invalid program: unterminated string", 7, 12);
+    }
+
+    #[test]
+    fn parsed_cells_have_spans() {
+        let program_str = "(do\n  (print \"hi\")\n  '(1 foo))";
+        let program = parse(program_str.to_string()).unwrap();
+
+        let entry = program.forms[0].clone();
+        assert_eq!(program.span_of(&entry), Some(Span { start: 0, end: 30, line: 1, col: 1 }));
+
+        let forms = match *entry.borrow() {
+            LispCell::List(ref list) => LispList::to_vec(list.clone()),
+            _ => panic!("Expected entry to be a list"),
+        };
+
+        assert_eq!(program.span_of(&forms[1]), Some(Span { start: 6, end: 18, line: 2, col: 3 }));
+        assert_eq!(program.span_of(&forms[2]), Some(Span { start: 21, end: 29, line: 3, col: 3 }));
+        assert_eq!(&program.text[21..29], "'(1 foo)");
+    }
+
+    #[test]
+    fn runtime_errors_are_positioned() {
+        let program_str = "(do\n  (def x 1)\n  (+ x (list 2)))";
+        let program = parse(program_str.to_string()).unwrap();
+
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let err = exec_prog(env, program).unwrap_err();
+
+        assert_eq!(err.span, Some(Span { start: 18, end: 32, line: 3, col: 3 }));
+        assert_eq!(
+            err.render(program_str),
+            "type error: expected number, found list (2) (line 3, col 3)\n  |\n3 |   (+ x (list 2)))\n  |   ^^^^^^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn parse_and_print_quasiquotes() {
+        let program_str = "`(a ,b ,@(c d) `(e ,,f))";
+        let program = parse(program_str.to_string()).unwrap();
+
+        assert_eq!(print(&program), program_str);
+
+        match *program.forms[0].borrow() {
+            LispCell::Quasiquoted(_) => {}
+            _ => panic!("Expected a quasiquoted form"),
+        };
+    }
+
+    #[test]
+    fn quasiquote_fills_holes() {
+        run_exec_test_literal("(do (def x 1) (def xs (list 2 3)) `(a ,x ,@xs (b ,(+ x 1))))", "(a 1 2 3 (b 2))");
+        run_exec_test_literal("(do (def xs (list)) `(a ,@xs b))", "(a b)");
+        run_exec_test_literal("`(a 'b ,(+ 1 2))", "(a 'b 3)");
+        run_exec_test_literal("`foo", "foo");
+    }
+
+    #[test]
+    fn nested_quasiquotes() {
+        run_exec_test_literal("(do (def x 1) `(a `(b ,(c ,x))))", "(a `(b ,(c 1)))");
+        run_exec_test_literal("(do (def x 1) `(a `(b ,,x ,@(d))))", "(a `(b ,1 ,@(d)))");
+    }
+
+    #[test]
+    fn unquote_errors() {
+        run_exec_error_test(",x", LispError::syntax("unquote used outside of a quasiquote"));
+        run_exec_error_test("`,@x", LispError::syntax("unquote-splicing used outside of a list"));
+        run_exec_error_test("`(a ,@1)", LispError::type_error("list to splice", &make_int(1)));
+    }
+
+    #[test]
+    fn defmacro_expands_then_evaluates() {
+        run_exec_test_literal("(defmacro my-unless (pred a b) `(if ,pred ,b ,a)) (my-unless (eq 1 2) 10 20)", "10");
+        run_exec_test_literal(
+            "(defmacro swap-args (call) `(,(car call) ,@(cdr (cdr call)) ,(car (cdr call)))) (swap-args (- 1 10))",
+            "9",
+        );
+    }
+
+    #[test]
+    fn macro_arguments_are_unevaluated() {
+        run_exec_test_literal("(defmacro quote-it (x) `',x) (quote-it (undefined symbols))", "(undefined symbols)");
+    }
+
+    #[test]
+    fn macroexpand_and_macroexpand_1() {
+        let prelude = "(defmacro my-unless (pred a b) `(if ,pred ,b ,a)) \
+                       (defmacro my-not-unless (pred a b) `(my-unless ,pred ,b ,a)) ";
+
+        run_exec_test_literal(&format!("{}(macroexpand-1 '(my-not-unless x 1 2))", prelude), "(my-unless x 2 1)");
+        run_exec_test_literal(&format!("{}(macroexpand '(my-not-unless x 1 2))", prelude), "(if x 1 2)");
+        run_exec_test_literal(&format!("{}(macroexpand '(+ 1 2))", prelude), "(+ 1 2)");
+    }
+
+    #[test]
+    fn gensym_makes_unique_symbols() {
+        run_exec_test("(eq (gensym) (gensym))", make_bool(false));
+
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let result = exec_prog(env, parse("(gensym \"tmp\")".to_string()).unwrap()).unwrap();
+        match *result.borrow() {
+            LispCell::Atom(ref symbol) => assert!(symbol.starts_with("tmp__"), "Unexpected gensym {}", symbol),
+            _ => panic!("Expected gensym to return a symbol"),
+        };
+    }
+
+    #[test]
+    fn recursive_calls_get_their_own_frames() {
+        run_exec_test_literal("(defn fact (n) (if (eq n 0) 1 (* n (fact (- n 1))))) (fact 5)", "120");
+        run_exec_test_literal(
+            "(defn fib (n) (if (eq n 0) 0 (if (eq n 1) 1 (+ (fib (- n 1)) (fib (- n 2)))))) (fib 10)",
+            "55",
+        );
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        run_exec_test(
+            "(defn even? (n) (if (eq n 0) (eq 1 1) (odd? (- n 1)))) \
+             (defn odd? (n) (if (eq n 0) (eq 1 2) (even? (- n 1)))) \
+             (list (even? 10) (odd? 7) (even? 3))",
+            make_list(vec![make_bool(true), make_bool(true), make_bool(false)]),
+        );
+    }
+
+    #[test]
+    fn late_bound_globals_are_visible_to_closures() {
+        run_exec_test_literal("(def get-y (lambda () (+ y 0))) (def y 42) (get-y)", "42");
+        run_exec_test_literal("(defn get-z () z) (def z 1) (def z 2) (get-z)", "2");
+    }
+
+    #[test]
+    fn closures_capture_their_own_environment() {
+        run_exec_test_literal(
+            "(defn make-adder (n) (lambda (x) (+ x n))) \
+             (def add1 (make-adder 1)) \
+             (def add5 (make-adder 5)) \
+             (list (add1 1) (add5 1))",
+            "(2 6)",
+        );
+    }
+
+    #[test]
+    fn call_frames_do_not_leak() {
+        run_exec_error_test("(defn foo (x) x) (foo 1) x", LispError::unbound_symbol("x"));
+    }
+
+    #[test]
+    fn tail_calls_run_in_constant_stack() {
+        run_exec_test_literal(
+            "(defn count-down (n) (if (eq n 0) 'done (count-down (- n 1)))) (count-down 1000000)",
+            "done",
+        );
+        run_exec_test_literal(
+            "(defn sum-to (n acc) (if (eq n 0) acc (do (def unused n) (sum-to (- n 1) (+ acc n))))) (sum-to 1000 0)",
+            "500500",
+        );
+    }
+
+    #[test]
+    fn mutual_tail_calls_run_in_constant_stack() {
+        run_exec_test(
+            "(defn even? (n) (if (eq n 0) (eq 1 1) (odd? (- n 1)))) \
+             (defn odd? (n) (if (eq n 0) (eq 1 2) (even? (- n 1)))) \
+             (even? 100001)",
+            make_bool(false),
+        );
+    }
+
+    #[test]
+    fn deep_non_tail_recursion() {
+        run_exec_test_literal("(defn depth (n) (if (eq n 0) 0 (+ 1 (depth (- n 1))))) (depth 100000)", "100000");
+    }
+
+    #[test]
+    fn deeply_nested_expressions() {
+        let depth = 50000;
+        let program_str = format!("{}0{}", "(+ 1 ".repeat(depth), ")".repeat(depth));
+
+        run_exec_test_literal(&program_str, "50000");
+    }
+
+    #[test]
+    fn long_bodies() {
+        let program_str = format!("(defn f () (do {} 'done)) (f)", "(+ 1 2) ".repeat(50000));
+
+        run_exec_test_literal(&program_str, "done");
+    }
+
+    const NEST: &str = "(defn nest (n acc) (if (eq n 0) acc (nest (- n 1) (list acc))))";
+
+    fn exec_nest_test(expr: &str) -> LispResult {
+        let program = parse(format!("{} {}", NEST, expr)).unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        exec_prog(env, program)
+    }
+
+    #[test]
+    fn deeply_nested_lists_print() {
+        let printed = print_cell(exec_nest_test("(nest 200000 1)").unwrap());
+
+        assert_eq!(printed, format!("{}1{}", "(".repeat(200000), ")".repeat(200000)));
+    }
+
+    #[test]
+    fn deeply_nested_lists_compare() {
+        let same = exec_nest_test("(eq (nest 200000 1) (nest 200000 1))").unwrap();
+        let different = exec_nest_test("(eq (nest 200000 1) (nest 200000 2))").unwrap();
+
+        assert_eq!(*same, *make_bool(true));
+        assert_eq!(*different, *make_bool(false));
+    }
+
+    #[test]
+    fn deeply_nested_lists_describe() {
+        let err = exec_nest_test("(+ 1 (nest 200000 1))").unwrap_err();
+
+        match err.kind {
+            LispErrorKind::Type { ref found, .. } => assert!(found.starts_with("list ((((")),
+            _ => panic!("expected a type error, got {}", err.kind),
+        }
+    }
+
+    #[test]
+    fn deeply_nested_quasiquote_templates() {
+        let depth = 100000;
+        let program_str = format!("(def x 1) `{}(a ,x ,@(list 2 3)){}", "(".repeat(depth), ")".repeat(depth));
+        let program = parse(program_str).unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        let printed = print_cell(exec_prog(env, program).unwrap());
+        assert_eq!(printed, format!("{}(a 1 2 3){}", "(".repeat(depth), ")".repeat(depth)));
+    }
+
+    #[test]
+    fn deeply_nested_quasiquotes() {
+        let depth = 100000;
+        let program_str = format!("(def x 1) {}{}x", "`".repeat(depth), ",".repeat(depth));
+        let program = parse(program_str).unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        let printed = print_cell(exec_prog(env, program).unwrap());
+        assert_eq!(printed, format!("{}{}1", "`".repeat(depth - 1), ",".repeat(depth - 1)));
+    }
+
+    #[test]
+    fn stack_depth_is_limited() {
+        let program_str = "(defn forever (n) (+ 1 (forever n)))\n(forever 0)";
+        let program = parse(program_str.to_string()).unwrap();
+
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let options = ExecOptions {
+            max_stack_depth: 1000,
+            ..ExecOptions::default()
+        };
+        let err = exec_prog_with_options(env, program, &options).unwrap_err();
+
+        assert_eq!(err.kind, LispErrorKind::StackDepthExceeded(1000));
+        assert_eq!(err.span, Some(Span { start: 23, end: 34, line: 1, col: 24 }));
+    }
+
+    #[test]
+    fn step_limit() {
+        let options = ExecOptions {
+            max_steps: Some(10000),
+            ..ExecOptions::default()
+        };
+
+        run_limited_exec_test(
+            "((lambda (f) (f f)) (lambda (f) (f f)))",
+            &options,
+            LispErrorKind::StepLimitExceeded(10000),
+        );
+    }
+
+    #[test]
+    fn cell_limit() {
+        let options = ExecOptions {
+            max_cells: Some(1000),
+            ..ExecOptions::default()
+        };
+
+        run_limited_exec_test(
+            "(defn grow (n) (grow (list n n n))) (grow 1)",
+            &options,
+            LispErrorKind::CellLimitExceeded(1000),
+        );
+    }
+
+    #[test]
+    fn timeout() {
+        let options = ExecOptions {
+            timeout: Some(Duration::from_millis(10)),
+            ..ExecOptions::default()
+        };
+
+        run_limited_exec_test(
+            "((lambda (f) (f f)) (lambda (f) (f f)))",
+            &options,
+            LispErrorKind::Timeout(Duration::from_millis(10)),
+        );
+    }
+
+    #[test]
+    fn limits_cover_the_whole_program() {
+        let options = ExecOptions {
+            max_steps: Some(10),
+            ..ExecOptions::default()
+        };
+
+        run_limited_exec_test("(+ 1 2) (+ 1 2) (+ 1 2)", &options, LispErrorKind::StepLimitExceeded(10));
+    }
+
+    #[test]
+    fn parse_and_print_number_literals() {
+        let program_str = "1 -5 1.0 3/4 -6/4 1e10 #x1F #b101 #o17 123456789012345678901234567890 inf";
+        let program = parse(program_str.to_string()).unwrap();
+
+        assert_eq!(
+            print(&program),
+            "1\n-5\n1.0\n3/4\n-3/2\n10000000000.0\n31\n5\n15\n123456789012345678901234567890\ninf"
+        );
+        assert_eq!(program.forms[10], make_atom("inf"));
+
+        run_parse_error_test("(+ 1/0 1)", "Invalid program: zero denominator in ratio", 3, 6);
+        run_parse_error_test("#xZZ", "Invalid program: malformed number literal", 0, 4);
+    }
+
+    #[test]
+    fn integers_are_exact() {
+        run_exec_test("(+ 16777216 1)", make_int(16777217));
+        run_exec_test("(- (* 9223372036854775807 2) 9223372036854775807)", make_int(9223372036854775807));
+        run_exec_test_literal("(* 9223372036854775807 2)", "18446744073709551614");
+        run_exec_test_literal("(- -9223372036854775807 2)", "-9223372036854775809");
+        run_exec_test_literal(
+            "(defn fact (n) (if (eq n 0) 1 (* n (fact (- n 1))))) (fact 30)",
+            "265252859812191058636308480000000",
+        );
+    }
+
+    #[test]
+    fn bignum_division() {
+        let a = "123456789012345678901234567890123";
+        let b = "98765432109876543210987654321";
+
+        run_exec_test_literal(
+            &format!("(* {} {})", a, b),
+            "12193263113702179522618503273374440481373261194926077834171483",
+        );
+        run_exec_test_literal(&format!("(/ (* {} {}) {})", a, b, b), a);
+        run_exec_test_literal(
+            &format!("(/ (+ (* {} {}) 1) {})", a, b, b),
+            "12193263113702179522618503273374440481373261194926077834171484/98765432109876543210987654321",
+        );
+        run_exec_test_literal(
+            &format!("(/ {} (* {} {}))", a, b, b),
+            "41152263004115226300411522630041/3251536859950210841957526799785500177818924960625929990347",
+        );
+        run_exec_test_literal(
+            "(/ (+ (* 4294967296 4294967296) 3) (* 4294967296 6))",
+            "18446744073709551619/25769803776",
+        );
+    }
+
+    #[test]
+    fn rationals() {
+        run_exec_test_literal("(/ 1 3)", "1/3");
+        run_exec_test_literal("(/ 6 4)", "3/2");
+        run_exec_test_literal("(+ 1/3 2/3)", "1");
+        run_exec_test_literal("(- 1/2 3/4)", "-1/4");
+        run_exec_test_literal("(- 1/2)", "-1/2");
+        run_exec_test_literal("(* 3/4 4)", "3");
+        run_exec_test_literal("(/ 3/4 -3/8)", "-2");
+        run_exec_test_literal("(/ 2)", "1/2");
+        run_exec_test_literal("(/ -3/4)", "-4/3");
+        run_exec_test("(/ 4.0)", make_float(0.25));
+        run_exec_error_test("(/ 0)", LispError::division_by_zero());
+    }
+
+    #[test]
+    fn floats_are_contagious() {
+        run_exec_test("(+ 1 1.0)", make_float(2.0));
+        run_exec_test("(/ 1.0 4)", make_float(0.25));
+        run_exec_test("(+ 1/2 0.5)", make_float(1.0));
+        run_exec_test_literal("(* 2 1.5)", "3.0");
+        run_exec_test("(/ 1.0 0)", make_float(f64::INFINITY));
+    }
+
+    #[test]
+    fn exact_division_by_zero() {
+        run_exec_error_test("(/ 1 0)", LispError::division_by_zero());
+        run_exec_error_test("(/ 1/2 0)", LispError::division_by_zero());
+    }
+
+    #[test]
+    fn exact_numbers_are_limited_in_size() {
+        let squares = "(defn sq (x n) (if (eq n 0) 1 (sq (* x x) (- n 1))))";
+
+        run_exec_error_test(&format!("{} (sq 3 22)", squares), LispError::number_too_large(MAX_EXACT_BITS));
+        run_exec_test_literal(
+            &format!("{} (try (sq 3 22) (catch :number-too-large e 'too-large))", squares),
+            "too-large",
+        );
+        run_exec_test_literal("(* 4294967296 4294967296 4294967296)", "79228162514264337593543950336");
+
+        let huge = "1".repeat(100_000);
+        run_parse_error_test(&huge, "Invalid program: number literal is too large", 0, 100_000);
+        run_parse_error_test(&format!("1/{}", huge), "Invalid program: number literal is too large", 0, 100_002);
+        run_exec_test_literal(&format!("{}1", "0".repeat(100_000)), "1");
+    }
+
+    #[test]
+    fn chained_comparisons() {
+        run_exec_test("(< 1 2 3)", make_bool(true));
+        run_exec_test("(< 1 3 2)", make_bool(false));
+        run_exec_test("(<= 1 1 2)", make_bool(true));
+        run_exec_test("(> 3 2 2)", make_bool(false));
+        run_exec_test("(>= 3 2 2)", make_bool(true));
+        run_exec_test("(< 1)", make_bool(true));
+        run_exec_test("(< 1/3 0.34 1)", make_bool(true));
+        run_exec_error_test("(<)", LispError::arity("<", "at least 1", 0));
+        run_exec_error_test("(< 1 'a)", LispError::type_error("number", &make_atom("a")));
+    }
+
+    #[test]
+    fn numeric_equality_crosses_types() {
+        run_exec_test("(= 1 1.0 2/2)", make_bool(true));
+        run_exec_test("(= 1/2 0.5)", make_bool(true));
+        run_exec_test("(= 1 2)", make_bool(false));
+        run_exec_test("(= (* 4294967296 4294967296) 18446744073709551616.0)", make_bool(true));
+        run_exec_test("(= (/ 0.0 0.0) (/ 0.0 0.0))", make_bool(false));
+    }
+
+    #[test]
+    fn integer_division() {
+        run_exec_test("(quot 7 2)", make_int(3));
+        run_exec_test("(quot -7 2)", make_int(-3));
+        run_exec_test("(rem -7 2)", make_int(-1));
+        run_exec_test("(rem 7 -2)", make_int(1));
+        run_exec_test("(mod -7 2)", make_int(1));
+        run_exec_test("(mod 7 -2)", make_int(-1));
+        run_exec_test("(mod 7.0 2)", make_float(1.0));
+        run_exec_error_test("(mod 7 0)", LispError::division_by_zero());
+        run_exec_error_test("(quot 7 0)", LispError::division_by_zero());
+        run_exec_error_test("(rem 5 2.5)", LispError::type_error("integer", &make_float(2.5)));
+        run_exec_error_test("(mod 7)", LispError::arity("mod", "2", 1));
+    }
+
+    #[test]
+    fn comparison_and_division_are_limited_in_size() {
+        let big = "(def big (* (expt 2 80000) (expt 2 80000) (expt 2 80000)))";
+
+        run_exec_test(&format!("{} (< big (+ big 1))", big), make_bool(true));
+        run_exec_test(&format!("{} (rem big 3)", big), make_int(1));
+        run_exec_error_test(
+            &format!("{} (< (/ 1 big) (/ 1 (+ big 1)))", big),
+            LispError::number_too_large(MAX_EXACT_BITS),
+        );
+        run_exec_error_test(&format!("{} (quot big (+ big 1))", big), LispError::number_too_large(MAX_EXACT_BITS));
+    }
+
+    #[test]
+    fn rounding() {
+        run_exec_test("(floor 7/2)", make_int(3));
+        run_exec_test("(ceil 7/2)", make_int(4));
+        run_exec_test("(floor -7/2)", make_int(-4));
+        run_exec_test("(round 5/2)", make_int(2));
+        run_exec_test("(round 7/2)", make_int(4));
+        run_exec_test("(round 2.5)", make_float(2.0));
+        run_exec_test("(round -3.5)", make_float(-4.0));
+        run_exec_test("(floor 2.7)", make_float(2.0));
+        run_exec_test_literal("(abs -7/2)", "7/2");
+    }
+
+    #[test]
+    fn min_and_max() {
+        run_exec_test("(min 3 1 2)", make_int(1));
+        run_exec_test("(max 3 1 2)", make_int(3));
+        run_exec_test("(max 1 2.0 3)", make_float(3.0));
+        run_exec_error_test("(min)", LispError::arity("min", "at least 1", 0));
+    }
+
+    #[test]
+    fn roots_and_powers() {
+        run_exec_test("(sqrt 16)", make_int(4));
+        run_exec_test("(sqrt 2)", make_float(2f64.sqrt()));
+        run_exec_test("(expt 2 10)", make_int(1024));
+        run_exec_test_literal("(expt 2 100)", "1267650600228229401496703205376");
+        run_exec_test_literal("(expt 2/3 3)", "8/27");
+        run_exec_test_literal("(expt 2 -2)", "1/4");
+        run_exec_test("(expt 4 0.5)", make_float(2.0));
+        run_exec_error_test("(expt 0 -1)", LispError::division_by_zero());
+        run_exec_error_test("(expt 3 500000)", LispError::number_too_large(MAX_EXACT_BITS));
+        run_exec_error_test("(expt 2 (* 4294967296 4294967296))", LispError::number_too_large(MAX_EXACT_BITS));
+        run_exec_test("(expt -1 (+ (* 4294967296 4294967296) 1))", make_int(-1));
+        run_exec_test("(exp 0)", make_float(1.0));
+        run_exec_test("(log 1)", make_float(0.0));
+        run_exec_test("(log 8 2)", make_float(3.0));
+        run_exec_test("(atan 1 1)", make_float(::std::f64::consts::FRAC_PI_4));
+        run_exec_test("(cos 0)", make_float(1.0));
+        run_exec_error_test("(sin 'a)", LispError::type_error("number", &make_atom("a")));
     }
 
-    fn run_exec_test_literal<'a, 'b>(prog_str: &'a str, expected_result_str: &'b str) {
-        let expected_result = parse(expected_result_str.to_string()).entry.unwrap();
+    #[test]
+    fn number_predicates() {
+        run_exec_test("(number? 1/2)", make_bool(true));
+        run_exec_test("(number? 'a)", make_bool(false));
+        run_exec_test("(integer? 4)", make_bool(true));
+        run_exec_test("(integer? 4.0)", make_bool(true));
+        run_exec_test("(integer? 1/2)", make_bool(false));
+        run_exec_test("(rational? 1/2)", make_bool(true));
+        run_exec_test("(float? 1/2)", make_bool(false));
+        run_exec_test("(float? 0.5)", make_bool(true));
+    }
+
+    #[test]
+    fn parse_and_print_bool_and_nil_literals() {
+        let program = parse("#t #f true false nil (eq 1 1)".to_string()).unwrap();
+
+        assert_eq!(print(&program), "true\nfalse\ntrue\nfalse\n()\n(eq 1 1)");
+        assert_eq!(program.forms[0], make_bool(true));
+        assert_eq!(program.forms[4], make_list(vec![]));
+    }
+
+    #[test]
+    fn truthiness() {
+        run_exec_test_literal("(if 0 1 2)", "1");
+        run_exec_test_literal("(if \"\" 1 2)", "1");
+        run_exec_test_literal("(if (list 1) 1 2)", "1");
+        run_exec_test_literal("(if nil 1 2)", "2");
+        run_exec_test_literal("(if () 1 2)", "2");
+        run_exec_test_literal("(if #f 1 2)", "2");
+        run_exec_test_literal("(not nil)", "true");
+        run_exec_test_literal("(not 0)", "false");
+    }
+
+    #[test]
+    fn and_or_short_circuit() {
+        run_exec_test_literal("(and)", "true");
+        run_exec_test_literal("(or)", "false");
+        run_exec_test_literal("(and 1 2 3)", "3");
+        run_exec_test_literal("(and 1 nil (undefined))", "nil");
+        run_exec_test_literal("(or false nil 5)", "5");
+        run_exec_test_literal("(or 1 (undefined))", "1");
+        run_exec_test_literal("(or false nil)", "nil");
+        run_exec_error_test("(and 1 (undefined))", LispError::unbound_symbol("undefined"));
+    }
+
+    #[test]
+    fn when_and_unless() {
+        run_exec_test_literal("(when (< 1 2) 1 2)", "2");
+        run_exec_test_literal("(when (> 1 2) (undefined))", "nil");
+        run_exec_test_literal("(unless (> 1 2) 1)", "1");
+        run_exec_test_literal("(unless true 1)", "nil");
+        run_exec_test_literal("(do (def x 0) (when true (def x 1) (def x (+ x 1))) x)", "2");
+        run_exec_error_test("(when)", LispError::arity("when", "at least 1", 0));
+    }
+
+    #[test]
+    fn cond_with_else() {
+        let prog = "(defn sign (n) (cond ((< n 0) 'neg) ((= n 0) 'zero) (else 'pos)))";
+
+        run_exec_test_literal(&format!("{} (list (sign -5) (sign 0) (sign 5))", prog), "(neg zero pos)");
+        run_exec_test_literal("(cond ((< 2 1) 1) (5))", "5");
+        run_exec_test_literal("(cond (false (undefined)))", "nil");
+        run_exec_test_literal("(cond (true (def x 1) (+ x 1)))", "2");
+        run_exec_error_test("(cond 1)", LispError::syntax("cond clauses must be non-empty lists"));
+    }
+
+    #[test]
+    fn case_over_literals() {
+        let prog = "(defn kind (x) (case x ((1 2 3) 'small) (10 'ten) ('a 'quoted) (a 'symbol) (else 'other)))";
+
+        run_exec_test_literal(
+            &format!("{} (list (kind 2) (kind 10) (kind 'a) (kind 11))", prog),
+            "(small ten symbol other)",
+        );
+        run_exec_test_literal("(case \"b\" (\"a\" 1) (\"b\" 2))", "2");
+        run_exec_test_literal("(case 4 ((1 2) 1))", "nil");
+    }
+
+    #[test]
+    fn match_destructures_with_guards() {
+        let prog = "(defn describe (x)
+                      (match x
+                        (() 'empty)
+                        (0 'zero)
+                        ('foo 'foo)
+                        ((a b) :when (< a b) (list 'ascending a b))
+                        ((a b) (list 'pair a b))
+                        ((a (b _) &rest more) (list a b more))
+                        (n :when (and (number? n) (< n 0)) 'negative)
+                        (_ 'other)))";
+
+        run_exec_test_literal(&format!("{} (describe nil)", prog), "empty");
+        run_exec_test_literal(&format!("{} (describe 0)", prog), "zero");
+        run_exec_test_literal(&format!("{} (describe 'foo)", prog), "foo");
+        run_exec_test_literal(&format!("{} (describe (list 1 2))", prog), "(ascending 1 2)");
+        run_exec_test_literal(&format!("{} (describe (list 2 1))", prog), "(pair 2 1)");
+        run_exec_test_literal(&format!("{} (describe (list 1 (list 2 3) 4 5))", prog), "(1 2 (4 5))");
+        run_exec_test_literal(&format!("{} (describe -3)", prog), "negative");
+        run_exec_test_literal(&format!("{} (describe 'bar)", prog), "other");
+    }
+
+    #[test]
+    fn match_binds_in_a_child_env() {
+        run_exec_test_literal("(def a 1) (match (list 2 3) ((a b) (+ a b))) a", "1");
+    }
+
+    #[test]
+    fn match_keywords_and_constants_literally() {
+        run_exec_test_literal("(match ':foo (:bar 1) (x x))", ":foo");
+        run_exec_test_literal("(match ':bar (:bar 1) (x x))", "1");
+        run_exec_test_literal("(match false (true 1) (false 2) (x 3))", "2");
+        run_exec_test_literal("(match (list 1) (nil 'empty) ((:a) 'a) (x 'other))", "other");
+        run_exec_test_literal("(match (list ':a 2) ((:a n) n))", "2");
+    }
+
+    #[test]
+    fn match_rejects_repeated_variables() {
+        let err = LispError::syntax("a is bound more than once in the same match pattern");
+
+        run_exec_error_test("(match (list 1 1) ((a a) a))", err.clone());
+        run_exec_error_test("(match 1 (1 1) ((a (b &rest a)) a))", err);
+        run_exec_test_literal("(match (list 1 2) ((_ _) 'ok))", "ok");
+    }
+
+    #[test]
+    fn match_deeply_nested_patterns() {
+        let depth = 100_000;
+        let pattern = format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        let value = format!("'{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+        run_exec_test(&format!("(match {} ({} a))", value, pattern), make_int(1));
+        run_exec_test_literal(&format!("(match '((1)) ({} a) (_ 'other))", pattern), "other");
+        run_exec_error_test(
+            &format!("(match 1 ({}a a{} a))", "(".repeat(depth), ")".repeat(depth)),
+            LispError::syntax("a is bound more than once in the same match pattern"),
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_match() {
+        run_exec_error_test("(match 3 (1 'one) (2 'two))", LispError::no_match(&make_int(3)));
+        run_exec_error_test("(match (list 1) ((a b) a))", LispError::no_match(&make_list(vec![make_int(1)])));
+        run_exec_error_test(
+            "(match 1 ((a &rest) a))",
+            LispError::syntax("&rest must be followed by exactly one pattern"),
+        );
+    }
+
+    #[test]
+    fn let_binds_in_a_child_env() {
+        run_exec_test_literal("(let ((x 1) (y 2)) (+ x y))", "3");
+        run_exec_test_literal("(def x 10) (let ((x 1) (y x)) (list x y))", "(1 10)");
+        run_exec_test_literal("(def x 10) (let ((x 1)) (set! x 2) x)", "2");
+        run_exec_test_literal("(def x 10) (let ((x 1)) (set! x 2) x) x", "10");
+        run_exec_test_literal("(let () 1 2)", "2");
+        run_exec_error_test("(let ((x 1)) 1) x", LispError::unbound_symbol("x"));
+        run_exec_error_test("(let ((x)) x)", LispError::syntax("let bindings must be (name value) pairs"));
+    }
+
+    #[test]
+    fn let_star_sees_earlier_bindings() {
+        run_exec_test_literal("(let* ((x 1) (y (+ x 1))) (list x y))", "(1 2)");
+        run_exec_test_literal("(let* ((x 1) (f (lambda () (+ x 0))) (x 2)) (list (f) x))", "(1 2)");
+    }
+
+    #[test]
+    fn letrec_allows_mutual_recursion() {
+        let prog = "(letrec ((even? (lambda (n) (if (= n 0) true (odd? (- n 1)))))
+                             (odd? (lambda (n) (if (= n 0) false (even? (- n 1))))))
+                      (list (even? 10) (odd? 7) (even? 3)))";
+
+        run_exec_test_literal(prog, "(true true false)");
+    }
+
+    #[test]
+    fn named_let_loops() {
+        run_exec_test_literal("(let loop ((i 0) (acc 0)) (if (> i 10) acc (loop (+ i 1) (+ acc i))))", "55");
+        run_exec_test_literal("(let loop ((i 0)) (if (< i 1000000) (loop (+ i 1)) i))", "1000000");
+        run_exec_error_test("(let loop ((i 0)) i) loop", LispError::unbound_symbol("loop"));
+    }
+
+    #[test]
+    fn def_defines_at_top_level() {
+        run_exec_test_literal("(defn setup () (def configured true)) (setup) configured", "true");
+        run_exec_test_literal("(let ((x 1)) (def y (+ x 1))) y", "2");
+        run_exec_test_literal("(defn outer () (defn inner () 5)) (outer) (inner)", "5");
+    }
+
+    #[test]
+    fn set_mutates_the_nearest_binding() {
+        run_exec_test_literal("(def counter 0) (defn bump () (set! counter (+ counter 1))) (bump) (bump) counter", "2");
+        run_exec_test_literal(
+            "(defn make-counter () (let ((n 0)) (lambda () (set! n (+ n 1))))) \
+             (def c (make-counter)) (c) (c) (list (c) ((make-counter)))",
+            "(3 1)",
+        );
+        run_exec_test_literal("(def a 1) (def b a) (set! b 2) (list a b)", "(1 2)");
+        run_exec_error_test("(set! undefined 1)", LispError::unbound_symbol("undefined"));
+        run_exec_error_test("(set! 1 1)", LispError::type_error("symbol to set", &make_int(1)));
+    }
+
+    #[test]
+    fn defonce_keeps_the_first_value() {
+        run_exec_test_literal("(defonce x 1) (defonce x (undefined)) x", "1");
+        run_exec_test_literal("(def x 1) (let ((y 2)) (defonce x y)) x", "1");
+        run_exec_test_literal("(let ((y 2)) (defonce z y)) z", "2");
+    }
+
+    #[test]
+    fn rest_params() {
+        run_exec_test_literal("(defn f (a &rest more) (list a more)) (list (f 1) (f 1 2 3))", "((1 ()) (1 (2 3)))");
+        run_exec_test_literal("(defn f (a . more) more) (f 1 2 3)", "(2 3)");
+        run_exec_test_literal("((lambda (&rest xs) (list xs)) 1 2)", "((1 2))");
+        run_exec_test_literal("(defmacro my-list (&rest xs) `(list ,@xs)) (my-list 1 (+ 1 1))", "(1 2)");
+        run_exec_error_test(
+            "(defn f (a &rest) a)",
+            LispError::syntax("parameters must be ordered as: required &optional &rest name &key"),
+        );
+    }
+
+    #[test]
+    fn optional_params_with_defaults() {
+        let prog = "(defn f (a &optional (b (* a 10)) c) (list a b c))";
+        let expected = "1 to 3 for (f a &optional (b (* a 10)) c)";
+
+        run_exec_test_literal(&format!("{} (list (f 1) (f 1 2) (f 1 2 3))", prog), "((1 10 ()) (1 2 ()) (1 2 3))");
+        run_exec_error_test(&format!("{} (f 1 2 3 4)", prog), LispError::arity("f", expected, 4));
+        run_exec_error_test(&format!("{} (f)", prog), LispError::arity("f", expected, 0));
+    }
+
+    #[test]
+    fn keyword_params() {
+        let prog = "(defn greet (name &key (greeting \"hi\") punct) (list greeting name punct))";
+        let signature = "(greet name &key (greeting \"hi\") punct)";
+
+        run_exec_test_literal(&format!("{} (greet 'bob)", prog), "(\"hi\" bob ())");
+        run_exec_test_literal(&format!("{} (greet 'bob :punct 1 :greeting 2)", prog), "(2 bob 1)");
+        run_exec_test_literal("(defn f (&optional a &key b) (list a b)) (list (f :b 1) (f 2 :b 3))", "((() 1) (2 3))");
+        run_exec_test_literal(":kw", ":kw");
+        run_exec_error_test(
+            &format!("{} (greet 'bob :volume 11)", prog),
+            LispError::type_error(&format!("keyword parameter of {}", signature), &make_atom(":volume")),
+        );
+        run_exec_error_test(
+            &format!("{} (greet 'bob :punct)", prog),
+            LispError::arity("greet", &format!("a value after :punct for {}", signature), 2),
+        );
+        run_exec_error_test("(defn f (&key a) a) (f :a)", LispError::arity("f", "a value after :a for (f &key a)", 1));
+    }
+
+    #[test]
+    fn arity_errors_name_the_signature() {
+        run_exec_error_test("(defn f () 1) (f 1)", LispError::arity("f", "0 for (f)", 1));
+        run_exec_error_test("((lambda (x y) (+ x y)) 1)", LispError::arity("lambda", "2 for (lambda x y)", 1));
+    }
+
+    #[test]
+    fn destructuring_params() {
+        run_exec_test_literal("(defn swap ((a b)) (list b a)) (swap (list 1 2))", "(2 1)");
+        run_exec_test_literal(
+            "(defn f ((a (b c)) _ &rest (d . more)) (list a b c d more)) (f '(1 (2 3)) 0 4 5 6)",
+            "(1 2 3 4 (5 6))",
+        );
+        run_exec_test_literal("((lambda ((_ second)) (+ second 0)) '(1 2))", "2");
+        run_exec_test_literal("(defn f (&optional ((a b) '(1 2))) (+ a b)) (list (f) (f '(3 4)))", "(3 7)");
+    }
+
+    #[test]
+    fn destructuring_let() {
+        run_exec_test_literal("(let (((a b) (list 1 2)) (c 3)) (list a b c))", "(1 2 3)");
+        run_exec_test_literal("(let* (((a &rest more) '(1 2 3)) ((b c) more)) (list a b c))", "(1 2 3)");
+        run_exec_test_literal(
+            "(let loop (((n . _) '(3 4)) (acc 1)) (if (= n 0) acc (loop (list (- n 1)) (* acc n))))",
+            "6",
+        );
+    }
+
+    #[test]
+    fn destructuring_shape_mismatch() {
+        run_exec_error_test(
+            "(let (((a b) (list 1 2 3))) a)",
+            LispError::type_error("list matching (a b)", &make_list(vec![make_int(1), make_int(2), make_int(3)])),
+        );
+        run_exec_error_test(
+            "(defn f ((a (b c))) b) (f '(1 2))",
+            LispError::type_error("list matching (b c)", &make_int(2)),
+        );
+        run_exec_error_test(
+            "(defn f ((a &rest)) a)",
+            LispError::syntax("&rest must be followed by exactly one pattern"),
+        );
+        run_exec_error_test("(let ((1 2)) 1)", LispError::type_error("symbol or list to bind", &make_int(1)));
+    }
+
+    #[test]
+    fn destructuring_deeply_nested_patterns() {
+        let depth = 100_000;
+        let pattern = format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        let value = format!("'{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+        run_exec_test(&format!("(let (({} {})) a)", pattern, value), make_int(1));
+        run_exec_test(&format!("(defn f ({} &rest b) a) (f {} 2)", pattern, value), make_int(1));
+        run_exec_error_test(
+            &format!("(let (({} '{}1 2{})) a)", pattern, "(".repeat(depth), ")".repeat(depth)),
+            LispError::type_error("list matching (a)", &make_list(vec![make_int(1), make_int(2)])),
+        );
+    }
+
+    #[test]
+    fn implicit_do_bodies() {
+        run_exec_test_literal("(defn f (x) (def seen x) (+ x 1)) (list (f 1) seen)", "(2 1)");
+        run_exec_test_literal("((lambda (x) (def y x) (* y 2)) 4)", "8");
+        run_exec_test_literal(
+            "(defmacro twice (form) (def unused 0) `(do ,form ,form)) (def n 0) (twice (set! n (+ n 1))) n",
+            "2",
+        );
+        run_exec_error_test("(lambda (x))", LispError::arity("lambda", "at least 2", 1));
+        run_exec_error_test("(defn f (x))", LispError::arity("defn", "at least 3", 2));
+    }
+
+    #[test]
+    fn atom_and_literal_bodies() {
+        run_exec_test_literal("((lambda (x) x) 5)", "5");
+        run_exec_test_literal("(defn answer () 42) (answer)", "42");
+        run_exec_test_literal("(defn greeting () \"hi\") (greeting)", "\"hi\"");
+    }
+
+    #[test]
+    fn docstrings() {
+        run_exec_test_literal("(defn sq (x) \"Squares x.\" (* x x)) (list (sq 3) (doc sq))", "(9 \"Squares x.\")");
+        run_exec_test_literal("(doc (lambda (x) \"Identity.\" x))", "\"Identity.\"");
+        run_exec_test_literal("(defmacro m () \"A macro.\" 1) (doc m)", "\"A macro.\"");
+        run_exec_test_literal("(defn f (x) x) (doc f)", "nil");
+        run_exec_error_test("(doc 1)", LispError::type_error("func", &make_int(1)));
+    }
+
+    #[test]
+    fn try_catch_by_kind() {
+        run_exec_test_literal(
+            "(try (car 1) (catch :type e (error-message e)))",
+            "\"type error: expected list, found number 1\"",
+        );
+        run_exec_test_literal("(try undefined (catch :type e 1) (catch :unbound-symbol e 2))", "2");
+        run_exec_test_literal(
+            "(try (throw 'oops) (catch :any e (list (error-kind e) (error-value e))))",
+            "(:user oops)",
+        );
+        run_exec_test_literal("(try (+ 1 2) (catch :any e 0))", "3");
+        run_exec_error_test("(try (throw 1) (catch :type e 0))", LispError::user(make_int(1)));
+    }
+
+    #[test]
+    fn try_catch_by_predicate() {
+        run_exec_test_literal(
+            "(defn big? (e) (> (error-value e) 10)) (list (try (throw 50) (catch big? e 'big) (catch :any e 'small)) \
+             (try (throw 5) (catch big? e 'big) (catch :any e 'small)))",
+            "(big small)",
+        );
+        run_exec_test_literal(
+            "(try (error \"bad\" 1 '(2)) (catch (lambda (e) #t) e (error-message e)))",
+            "\"bad 1 (2)\"",
+        );
+    }
+
+    #[test]
+    fn try_finally() {
+        run_exec_test_literal("(def log '()) (list (try 1 (finally (set! log (list 'done)))) log)", "(1 (done))");
+        run_exec_test_literal(
+            "(def log '()) (list (try (try (throw 1) (finally (set! log (list 'done)))) (catch :any e 2)) log)",
+            "(2 (done))",
+        );
+        run_exec_test_literal(
+            "(def n 0) (try (throw 1) (catch :any e (set! n (+ n 1))) (finally (set! n (* n 10)))) n",
+            "10",
+        );
+        run_exec_error_test("(try (throw 1) (finally 2))", LispError::user(make_int(1)));
+    }
+
+    #[test]
+    fn error_values() {
+        run_exec_test_literal("(list (error? (try (throw 1) (catch :any e e))) (error? 1))", "(#t #f)");
+        run_exec_test_literal("(try (throw 2) (catch :any e (try (throw e) (catch :user e2 (error-value e2)))))", "2");
+        run_exec_test_literal(
+            "(try (f 1 2) (catch :any e (list (error-kind e) (error-span e))))",
+            "(:unbound-symbol (1 7))",
+        );
+        run_exec_test_literal("(try (defn f (x) x) (f) (catch :arity e (error-kind e)))", ":arity");
+        run_exec_error_test("(error-message 1)", LispError::type_error("error", &make_int(1)));
+        run_exec_error_test(
+            "(try (catch :any e 1) 2)",
+            LispError::syntax("a try's body must come before its catch clauses"),
+        );
+    }
+
+    #[test]
+    fn limits_cannot_be_caught() {
+        let options = ExecOptions {
+            max_steps: Some(10000),
+            ..ExecOptions::default()
+        };
+
+        run_limited_exec_test(
+            "(defn spin () (try (spin) (catch :any e (spin)))) (spin)",
+            &options,
+            LispErrorKind::StepLimitExceeded(10000),
+        );
+    }
+
+    #[test]
+    fn restarts_from_handlers() {
+        let prog = "(defn clean (r) \
+                      (restart-case (if (< r 0) (error \"bad record\" r) r) \
+                        (use-value (v) v) \
+                        (skip-record () 'skipped))) \
+                    (list (handler-bind ((:user (lambda (e) (invoke-restart 'use-value 0)))) \
+                            (list (clean 1) (clean -1))) \
+                          (handler-bind ((:any (lambda (e) (invoke-restart 'skip-record)))) (clean -2)))";
+        run_exec_test_literal(prog, "((1 0) skipped)");
+        run_exec_test_literal(
+            "(handler-bind ((:unbound-symbol (lambda (e) (invoke-restart 'fix 42)))) \
+               (restart-case (+ 1 x) (fix (v) v)))",
+            "42",
+        );
+    }
+
+    #[test]
+    fn handlers_run_without_unwinding() {
+        run_exec_test_literal(
+            "(def seen '()) \
+             (list (handler-bind ((:any (lambda (e) (set! seen (list (error-value e)))))) (signal 'hello) 'after) \
+                   seen)",
+            "(after (hello))",
+        );
+        run_exec_test_literal(
+            "(def seen '()) \
+             (handler-bind ((:any (lambda (e) (set! seen (list 'outer seen))))) \
+               (handler-bind ((:any (lambda (e) (set! seen (list 'inner seen))))) (signal 1))) \
+             seen",
+            "(outer (inner ()))",
+        );
+        run_exec_test_literal("(signal 1)", "nil");
+        run_exec_error_test(
+            "(handler-bind ((:user (lambda (e) 'ignored))) (throw 1))",
+            LispError::user(make_int(1)),
+        );
+    }
+
+    #[test]
+    fn handlers_see_errors_around_trys() {
+        run_exec_test_literal(
+            "(def n 0) (handler-bind ((:any (lambda (e) (set! n 1)))) (try (throw 1) (catch :any e 2))) n",
+            "0",
+        );
+        run_exec_test_literal(
+            "(def n 0) (try (handler-bind ((:any (lambda (e) (set! n 1)))) (throw 1)) (catch :any e (+ n 1)))",
+            "2",
+        );
+        run_exec_test_literal(
+            "(def log '()) \
+             (list (restart-case (try (invoke-restart 'out 5) (catch :any e 'caught) (finally (set! log '(cleaned)))) \
+                     (out (v) v)) \
+                   log)",
+            "(5 (cleaned))",
+        );
+    }
+
+    #[test]
+    fn handlers_see_errors_once_through_cleanups() {
+        let counter = "(def n 0) (defn count (e) (set! n (+ n 1)))";
+
+        run_exec_test_literal(
+            &format!(
+                "{} (try (handler-bind ((:any count)) (try (throw 1) (finally (+ 1 1)))) (catch :any e n))",
+                counter
+            ),
+            "1",
+        );
+        run_exec_test_literal(
+            &format!(
+                "{} (try (handler-bind ((:any count)) (try (try (throw 1) (finally 1 2)) (finally (+ 1 1)))) \
+                 (catch :any e n))",
+                counter
+            ),
+            "1",
+        );
+        run_exec_test_literal(
+            &format!(
+                "{} (try (handler-bind ((:any count)) (try (throw 1) (finally (throw 2)))) (catch :any e n))",
+                counter
+            ),
+            "2",
+        );
+    }
+
+    #[test]
+    fn missing_restarts() {
+        run_exec_test_literal(
+            "(try (invoke-restart 'nowhere) (catch :no-restart e (error-message e)))",
+            "\"no active restart named nowhere\"",
+        );
+        run_exec_error_test("(restart-case 1 (2 () 3))", LispError::type_error("restart name", &make_int(2)));
+        run_exec_error_test(
+            "(handler-bind (:any) 1)",
+            LispError::syntax("handler-bind handlers must be (selector handler) pairs"),
+        );
+    }
+
+    #[test]
+    fn call_cc_escapes() {
+        run_exec_test_literal("(+ 1 (call/cc (lambda (k) (+ 10 (k 2)))))", "3");
+        run_exec_test_literal("(call/cc (lambda (return) (list 1 (return 2) 3)))", "2");
+        run_exec_test_literal("(call/cc (lambda (k) (try (k 1) (catch :any e 2))))", "1");
+        run_exec_test_literal("(call-with-current-continuation (lambda (k) 5))", "5");
+        run_exec_error_test("(call/cc 1)", LispError::type_error("func", &make_int(1)));
+    }
+
+    #[test]
+    fn call_cc_reentry() {
+        run_exec_test_literal(
+            "(def saved '()) (def total (+ 100 (call/cc (lambda (k) (set! saved k) 0)))) (saved 5) total",
+            "105",
+        );
+    }
+
+    #[test]
+    fn reset_and_shift() {
+        run_exec_test_literal("(reset (+ 1 (shift k (k (k 10)))))", "12");
+        run_exec_test_literal("(* 2 (reset (+ 1 (shift k 5))))", "10");
+        run_exec_test_literal("(reset (let ((x (shift k (list (k 1) (k 2))))) (* x 10)))", "(10 20)");
+        run_exec_test_literal(
+            "(def saved '()) (+ 1 (reset (* 2 (shift k (do (set! saved k) 1))))) (list (saved 10) (saved 3))",
+            "(20 6)",
+        );
+        run_exec_error_test("(shift k 1)", LispError::syntax("shift used outside of a reset"));
+    }
+
+    #[test]
+    fn generators_from_shift() {
+        run_exec_test_literal(
+            "(defn yield (x) (shift k (list x k))) \
+             (defn next (gen) ((car (cdr gen)) '())) \
+             (def g1 (reset (yield 1) (yield 2) 'done)) \
+             (def g2 (next g1)) \
+             (list (car g1) (car g2) (next g2))",
+            "(1 2 done)",
+        );
+    }
+
+    #[test]
+    fn continuations_resume_from_the_same_values() {
+        run_exec_test_literal("(reset (let ((a 1) (b (shift k (list (k 2) (k 3))))) (list a b)))", "((1 2) (1 3))");
+    }
+
+    #[test]
+    fn continuations_share_frames() {
+        let options = ExecOptions {
+            max_cells: Some(1_000_000),
+            ..ExecOptions::default()
+        };
+        let program_str = "(defn capture (n ks) (if (= n 0) 0 (capture (- n 1) (list (call/cc (lambda (k) k)) ks)))) \
+                           (defn deep (n) (if (= n 0) (capture 10000 '()) (+ 1 (deep (- n 1))))) \
+                           (deep 10000)";
+        let program = parse(program_str.to_string()).unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        let result = exec_prog_with_options(env, program, &options).unwrap();
+
+        assert_eq!(result, make_int(10000));
+    }
+
+    #[test]
+    fn resumed_frames_are_counted() {
+        let options = ExecOptions {
+            max_cells: Some(1_000_000),
+            ..ExecOptions::default()
+        };
+
+        run_limited_exec_test(
+            "(def saved '()) (def count 0) \
+             (defn deep (n) (if (= n 0) (call/cc (lambda (k) (set! saved k) 0)) (+ 1 (deep (- n 1))))) \
+             (do (deep 10000) (set! count (+ count 1)) (if (< count 100) (saved 0) count))",
+            &options,
+            LispErrorKind::CellLimitExceeded(1_000_000),
+        );
+    }
+
+    fn run_exec_test_literal(prog_str: &str, expected_result_str: &str) {
+        let expected_result = parse(expected_result_str.to_string()).unwrap().forms.pop().unwrap();
 
         run_exec_test(prog_str, expected_result)
     }
 
-    fn run_exec_test<'a>(prog_str: &'a str, expected_result: LispCellRef) {
-        let program = parse(prog_str.to_string());
+    fn run_exec_test(prog_str: &str, expected_result: LispCellRef) {
+        let program = parse(prog_str.to_string()).unwrap();
 
-        let mut env = Rc::new(RefCell::new(Environment::new()));
-        let result = exec_prog(env, program);
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let result = exec_prog(env, program).unwrap();
         println!("result: {:?}", &result);
         println!("pretty result: {:?}", print_cell(result.clone()));
 
         assert_eq!(*result, *expected_result);
     }
+
+    fn run_exec_error_test(prog_str: &str, expected_error: LispError) {
+        let program = parse(prog_str.to_string()).unwrap();
+
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let result = exec_prog(env, program);
+
+        assert_eq!(result.map_err(|err| err.kind), Err(expected_error.kind));
+    }
+
+    #[test]
+    fn parsed_quotes_are_counted() {
+        let before = allocated_cells();
+        parse("'a `b".to_string()).unwrap();
+
+        assert_eq!(allocated_cells() - before, 4);
+    }
+
+    #[test]
+    fn limits_cover_parsing() {
+        let options = ExecOptions {
+            max_cells: Some(1000),
+            ..ExecOptions::default()
+        };
+        let program_str = format!("'({})", "a ".repeat(1000));
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        let result = parse_and_exec_with_options(env, program_str, &options);
+
+        assert_eq!(result.map_err(|err| err.kind), Err(LispErrorKind::CellLimitExceeded(1000)));
+    }
+
+    fn run_limited_exec_test(prog_str: &str, options: &ExecOptions, expected_kind: LispErrorKind) {
+        let program = parse(prog_str.to_string()).unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        let result = exec_prog_with_options(env, program, options);
+
+        assert_eq!(result.map_err(|err| err.kind), Err(expected_kind));
+    }
+
+    fn run_parse_error_test<'a>(prog_str: &'a str, expected_msg: &'a str, start: usize, end: usize) {
+        let err = parse(prog_str.to_string()).unwrap_err();
+
+        assert_eq!(err.kind, LispErrorKind::Parse(expected_msg.to_string()));
+        assert_eq!(err.span.map(|span| (span.start, span.end)), Some((start, end)));
+    }
 }
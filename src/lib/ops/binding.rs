@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::core;
+use super::{
+    dew, eval_each, DefnFuncExecutorImpl, Environment, LispCell, LispCellRef, LispError, LispFunc, LispFuncType,
+    LispList, LispStep, ParamList, Pattern,
+};
+
+type Bindings = Vec<(Pattern, LispCellRef)>;
+
+/// Evaluates each binding's value in the enclosing environment, then runs the body in a child
+/// environment holding all of them. With a name before the bindings, the body can also call itself
+/// by that name with new values for the bindings.
+pub fn let_op(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let loop_name = match args.first() {
+        Some(first) => match *first.borrow() {
+            LispCell::Atom(ref name) => Some(name.clone()),
+            _ => None,
+        },
+        None => return Err(LispError::arity("let", "at least 1", 0)),
+    };
+
+    if let Some(loop_name) = loop_name {
+        return named_let(env, loop_name, &args[1..]);
+    }
+
+    let (bindings, body) = split_bindings("let", args)?;
+    let (patterns, inits): (Vec<Pattern>, Vec<LispCellRef>) = bindings.into_iter().unzip();
+    let body_env = env.clone();
+
+    eval_each(env, inits, move |values| {
+        let mut frame = Environment::new_child(body_env.clone());
+        for (pattern, value) in patterns.iter().zip(values.iter()) {
+            pattern.bind(value, &mut frame)?;
+        }
+
+        dew(Rc::new(RefCell::new(frame)), &body)
+    })
+}
+
+/// Like `let`, but each binding's value is evaluated in an environment holding the ones before it.
+pub fn let_star(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let (bindings, body) = split_bindings("let*", args)?;
+
+    bind_nested(env, Rc::new(bindings), 0, Rc::new(body))
+}
+
+/// Like `let`, but every binding is visible to all of the values, so functions bound here can
+/// refer to themselves and each other.
+pub fn letrec(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let (bindings, body) = split_bindings("letrec", args)?;
+
+    let mut frame = Environment::new_child(env);
+    for name in bindings.iter().flat_map(|(pattern, _)| pattern.names()) {
+        frame.def(name, core::lisp_null());
+    }
+
+    bind_in_place(Rc::new(RefCell::new(frame)), Rc::new(bindings), 0, Rc::new(body))
+}
+
+fn named_let(
+    env: Rc<RefCell<Environment>>,
+    loop_name: String,
+    args: &[LispCellRef],
+) -> Result<LispStep, LispError> {
+    let (bindings, body) = split_bindings("let", args)?;
+    let (patterns, inits): (Vec<Pattern>, Vec<LispCellRef>) = bindings.into_iter().unzip();
+
+    // The loop function closes over a frame holding only itself, so the body sees the bindings
+    // and the loop name but nothing leaks into `env`
+    let loop_env = Rc::new(RefCell::new(Environment::new_child(env.clone())));
+    let func = LispFunc::new(
+        loop_name.clone(),
+        LispFuncType::Normal,
+        Box::new(DefnFuncExecutorImpl {
+            name: loop_name.clone(),
+            func_body: body,
+            params: ParamList::from_patterns(patterns),
+            env: loop_env.clone(),
+        }),
+    );
+
+    let (executor, call_env) = (func.func_executor.clone(), env.clone());
+    loop_env.borrow_mut().def(loop_name, LispCell::Func(func).to_ref());
+
+    eval_each(env, inits, move |values| executor.exec_step(call_env.clone(), &values))
+}
+
+/// Evaluates each value in turn, binding it in a new child of the environment the previous one was
+/// bound in.
+fn bind_nested(
+    env: Rc<RefCell<Environment>>,
+    bindings: Rc<Bindings>,
+    index: usize,
+    body: Rc<Vec<LispCellRef>>,
+) -> Result<LispStep, LispError> {
+    let init = match bindings.get(index) {
+        Some((_, init)) => init.clone(),
+        None => return dew(env, &body),
+    };
+
+    let outer_env = env.clone();
+
+    Ok(LispStep::eval_then(env, init, move |value| {
+        let mut frame = Environment::new_child(outer_env.clone());
+        bindings[index].0.bind(&value, &mut frame)?;
+
+        bind_nested(Rc::new(RefCell::new(frame)), bindings.clone(), index + 1, body.clone())
+    }))
+}
+
+/// Evaluates each value in turn in `frame`, binding it there before moving on to the next.
+pub fn bind_in_place(
+    frame: Rc<RefCell<Environment>>,
+    bindings: Rc<Bindings>,
+    index: usize,
+    body: Rc<Vec<LispCellRef>>,
+) -> Result<LispStep, LispError> {
+    let init = match bindings.get(index) {
+        Some((_, init)) => init.clone(),
+        None => return dew(frame, &body),
+    };
+
+    let next_frame = frame.clone();
+
+    Ok(LispStep::eval_then(frame, init, move |value| {
+        bindings[index].0.bind(&value, &mut next_frame.borrow_mut())?;
+
+        bind_in_place(next_frame.clone(), bindings.clone(), index + 1, body.clone())
+    }))
+}
+
+/// Splits the args of a binding form into its `((pattern value) ...)` bindings and its body.
+fn split_bindings(name: &str, args: &[LispCellRef]) -> Result<(Bindings, Vec<LispCellRef>), LispError> {
+    let (bindings, body) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity(name, "at least 1", 0)),
+    };
+
+    let bindings = match *bindings.borrow() {
+        LispCell::List(ref bindings) => LispList::to_vec(bindings.clone()),
+        _ => return Err(LispError::type_error("list of bindings", bindings)),
+    };
+
+    let bindings = bindings.iter().map(|binding| to_binding(name, binding)).collect::<Result<Vec<_>, _>>()?;
+
+    Ok((bindings, body.to_vec()))
+}
+
+fn to_binding(name: &str, binding: &LispCellRef) -> Result<(Pattern, LispCellRef), LispError> {
+    if let LispCell::List(ref parts) = *binding.borrow() {
+        if let [pattern, value] = LispList::to_vec(parts.clone()).as_slice() {
+            return Ok((Pattern::parse(pattern)?, value.clone()));
+        }
+    }
+
+    Err(LispError::syntax(&format!("{} bindings must be (name value) pairs", name)).with_form(binding))
+}
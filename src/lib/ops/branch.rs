@@ -0,0 +1,271 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::core;
+use super::{dew, Environment, LispCell, LispCellRef, LispError, LispList, LispStep};
+
+pub fn cond(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let clauses = args.iter().map(|clause| to_clause("cond", clause)).collect::<Result<Vec<_>, _>>()?;
+
+    cond_from(env, Rc::new(clauses), 0)
+}
+
+pub fn case(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let (key, clauses) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity("case", "at least 1", 0)),
+    };
+
+    let clauses = clauses.iter().map(|clause| to_clause("case", clause)).collect::<Result<Vec<_>, _>>()?;
+    let body_env = env.clone();
+
+    Ok(LispStep::eval_then(env, key.clone(), move |key| {
+        for (datums, body) in clauses.iter() {
+            let is_match = match *datums.borrow() {
+                LispCell::Atom(ref name) if name == "else" => true,
+                LispCell::List(ref datums) => LispList::to_vec(datums.clone()).contains(&key),
+                _ => *datums == key,
+            };
+
+            if is_match {
+                return dew(body_env.clone(), body);
+            }
+        }
+
+        Ok(LispStep::Done(core::lisp_null()))
+    }))
+}
+
+/// Matches a value against each clause's pattern in turn, running the body of the first one that
+/// matches and whose guard (given after `:when`) holds, in a child environment holding the
+/// pattern's bindings.
+pub fn match_op(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let (value, clauses) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity("match", "at least 1", 0)),
+    };
+
+    let clauses = clauses.iter().map(to_match_clause).collect::<Result<Vec<_>, _>>()?;
+    let (match_env, clauses) = (env.clone(), Rc::new(clauses));
+
+    Ok(LispStep::eval_then(env, value.clone(), move |value| {
+        match_from(match_env.clone(), value, clauses.clone(), 0)
+    }))
+}
+
+struct MatchClause {
+    pattern: LispCellRef,
+    guard: Option<LispCellRef>,
+    body: Vec<LispCellRef>,
+}
+
+fn cond_from(
+    env: Rc<RefCell<Environment>>,
+    clauses: Rc<Vec<(LispCellRef, Vec<LispCellRef>)>>,
+    index: usize,
+) -> Result<LispStep, LispError> {
+    let (test, body) = match clauses.get(index) {
+        Some(clause) => clause.clone(),
+        None => return Ok(LispStep::Done(core::lisp_null())),
+    };
+
+    if let LispCell::Atom(ref name) = *test.borrow() {
+        if name == "else" {
+            return dew(env, &body);
+        }
+    }
+
+    let body_env = env.clone();
+
+    Ok(LispStep::eval_then(env, test, move |test_result| {
+        match (test_result.borrow().is_truthy(), body.is_empty()) {
+            (true, true) => Ok(LispStep::Done(test_result.clone())),
+            (true, false) => dew(body_env.clone(), &body),
+            (false, _) => cond_from(body_env.clone(), clauses.clone(), index + 1),
+        }
+    }))
+}
+
+fn match_from(
+    env: Rc<RefCell<Environment>>,
+    value: LispCellRef,
+    clauses: Rc<Vec<MatchClause>>,
+    index: usize,
+) -> Result<LispStep, LispError> {
+    for (index, clause) in clauses.iter().enumerate().skip(index) {
+        let mut bindings = vec![];
+
+        if !match_pattern(&clause.pattern, &value, &mut bindings)? {
+            continue;
+        }
+
+        let mut frame = Environment::new_child(env.clone());
+        for (name, bound) in bindings {
+            frame.def(name, bound);
+        }
+
+        let frame = Rc::new(RefCell::new(frame));
+
+        return match clause.guard {
+            None => dew(frame, &clause.body),
+            Some(ref guard) => {
+                let (env, value, clauses) = (env.clone(), value.clone(), clauses.clone());
+
+                Ok(LispStep::eval_then(frame.clone(), guard.clone(), move |guard_result| {
+                    match guard_result.borrow().is_truthy() {
+                        true => dew(frame.clone(), &clauses[index].body),
+                        false => match_from(env.clone(), value.clone(), clauses.clone(), index + 1),
+                    }
+                }))
+            }
+        };
+    }
+
+    Err(LispError::no_match(&value))
+}
+
+/// Checks whether `value` has the shape of `pattern`, collecting the values bound by symbols in
+/// the pattern. `_` matches anything, quoted patterns, keywords and literals (including `true`,
+/// `false` and `nil`) match equal values, and list patterns match lists element by element, with
+/// `&rest name` binding whatever is left over.
+///
+/// Patterns can be nested as deeply as the values they match, so the parts still to match are kept
+/// on an explicit stack rather than recursed into.
+fn match_pattern(
+    pattern: &LispCellRef,
+    value: &LispCellRef,
+    bindings: &mut Vec<(String, LispCellRef)>,
+) -> Result<bool, LispError> {
+    let mut pending = vec![(pattern.clone(), value.clone())];
+
+    while let Some((pattern, value)) = pending.pop() {
+        let is_match = match *pattern.borrow() {
+            LispCell::Atom(ref name) if name == "_" => true,
+            LispCell::Atom(ref name) if is_keyword(name) => pattern == value,
+            LispCell::Atom(ref name) => {
+                bindings.push((name.clone(), value.clone()));
+
+                true
+            }
+            LispCell::Number(_) | LispCell::Str(_) | LispCell::Bool(_) => pattern == value,
+            LispCell::Quoted(ref quoted) => *quoted == value,
+            LispCell::List(ref patterns) => {
+                let patterns = LispList::to_vec(patterns.clone());
+                let (patterns, rest) = split_rest_pattern(&patterns)?;
+
+                let values = match *value.borrow() {
+                    LispCell::List(ref values) => LispList::to_vec(values.clone()),
+                    _ => return Ok(false),
+                };
+
+                let has_right_len = match rest {
+                    Some(_) => values.len() >= patterns.len(),
+                    None => values.len() == patterns.len(),
+                };
+
+                if !has_right_len {
+                    return Ok(false);
+                }
+
+                // The parts are matched from the top of the stack, so they go on last to first
+                if let Some(rest) = rest {
+                    pending.push((rest, LispCell::new_list(values[patterns.len()..].to_vec())));
+                }
+
+                pending.extend(patterns.iter().cloned().zip(values).rev());
+
+                true
+            }
+            _ => {
+                let msg = "match patterns must be symbols, literals, quoted values or lists";
+
+                return Err(LispError::syntax(msg).with_form(&pattern));
+            }
+        };
+
+        if !is_match {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Checks that no symbol is bound more than once in `pattern`, adding the ones it binds to `names`.
+fn check_pattern_names(pattern: &LispCellRef, names: &mut Vec<String>) -> Result<(), LispError> {
+    let mut pending = vec![pattern.clone()];
+
+    while let Some(pattern) = pending.pop() {
+        match *pattern.borrow() {
+            LispCell::Atom(ref name) if name == "_" || name == "&rest" || is_keyword(name) => {}
+            LispCell::Atom(ref name) if names.contains(name) => {
+                let msg = format!("{} is bound more than once in the same match pattern", name);
+
+                return Err(LispError::syntax(&msg).with_form(&pattern));
+            }
+            LispCell::Atom(ref name) => names.push(name.clone()),
+            LispCell::List(ref patterns) => pending.extend(LispList::to_vec(patterns.clone()).into_iter().rev()),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn is_keyword(name: &str) -> bool {
+    name.len() > 1 && name.starts_with(':')
+}
+
+fn split_rest_pattern(patterns: &[LispCellRef]) -> Result<(&[LispCellRef], Option<LispCellRef>), LispError> {
+    let rest_index = patterns.iter().position(|pattern| match *pattern.borrow() {
+        LispCell::Atom(ref name) => name == "&rest",
+        _ => false,
+    });
+
+    match rest_index {
+        None => Ok((patterns, None)),
+        Some(index) if index + 2 == patterns.len() => Ok((&patterns[..index], Some(patterns[index + 1].clone()))),
+        Some(index) => {
+            Err(LispError::syntax("&rest must be followed by exactly one pattern").with_form(&patterns[index]))
+        }
+    }
+}
+
+fn to_clause(name: &str, clause: &LispCellRef) -> Result<(LispCellRef, Vec<LispCellRef>), LispError> {
+    let parts = match *clause.borrow() {
+        LispCell::List(ref parts) => LispList::to_vec(parts.clone()),
+        _ => vec![],
+    };
+
+    match parts.split_first() {
+        Some((head, body)) => Ok((head.clone(), body.to_vec())),
+        None => Err(LispError::syntax(&format!("{} clauses must be non-empty lists", name)).with_form(clause)),
+    }
+}
+
+fn to_match_clause(clause: &LispCellRef) -> Result<MatchClause, LispError> {
+    let (pattern, rest) = to_clause("match", clause)?;
+    check_pattern_names(&pattern, &mut vec![])?;
+
+    let is_guarded = match rest.first() {
+        Some(first) => match *first.borrow() {
+            LispCell::Atom(ref name) => name == ":when",
+            _ => false,
+        },
+        None => false,
+    };
+
+    match (is_guarded, rest.len()) {
+        (false, _) => Ok(MatchClause {
+            pattern,
+            guard: None,
+            body: rest,
+        }),
+        (true, n) if n >= 2 => Ok(MatchClause {
+            pattern,
+            guard: Some(rest[1].clone()),
+            body: rest[2..].to_vec(),
+        }),
+        (true, _) => Err(LispError::syntax(":when must be followed by a guard").with_form(clause)),
+    }
+}
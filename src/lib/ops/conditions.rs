@@ -0,0 +1,145 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::core;
+use super::errors::Selector;
+use super::{
+    dew, eval_each, make_func, Environment, LispCatches, LispCell, LispCellRef, LispError, LispErrorKind, LispFunc,
+    LispFuncType, LispList, LispResult, LispStep,
+};
+
+/// Hands `value` to the `handler-bind` handlers around it as a `:user` condition, without
+/// unwinding. Unlike `throw`, it returns nil if none of them invoke a restart.
+pub fn signal(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    match args {
+        [value] => match *value.borrow() {
+            LispCell::Error(ref err) => Ok(LispStep::Signal(err.clone())),
+            _ => Ok(LispStep::Signal(LispError::user(value.clone()))),
+        },
+        _ => Err(LispError::arity("signal", "1", args.len())),
+    }
+}
+
+/// Runs the body with handlers for the errors and conditions raised within it, given as
+/// `((selector handler) ...)` with selectors like those of `try`'s `catch` clauses. Each handler
+/// that applies is called with the error in turn, innermost first, while the code that raised it
+/// is still waiting. A handler can end that code by invoking a restart; if it returns instead,
+/// the error carries on to the next handler.
+pub fn handler_bind(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let (bindings, body) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity("handler-bind", "at least 1", 0)),
+    };
+
+    let bindings = match *bindings.borrow() {
+        LispCell::List(ref bindings) => LispList::to_vec(bindings.clone()),
+        _ => return Err(LispError::type_error("list of handlers", bindings)),
+    };
+
+    let (selectors, handlers): (Vec<Selector>, Vec<LispCellRef>) =
+        bindings.iter().map(to_handler_binding).collect::<Result<Vec<_>, _>>()?.into_iter().unzip();
+    let (body_env, body, selectors) = (env.clone(), body.to_vec(), Rc::new(selectors));
+
+    // The handlers are evaluated once, when they're bound
+    eval_each(env, handlers, move |handlers| {
+        let (handler_env, selectors, handlers) = (body_env.clone(), selectors.clone(), Rc::new(handlers));
+
+        Ok(LispStep::handle(dew(body_env.clone(), &body)?, move |err| {
+            handle_from(handler_env.clone(), err, selectors.clone(), handlers.clone(), 0)
+        }))
+    })
+}
+
+/// Evaluates `form`, making the restarts given after it as `(name (params...) body...)` available
+/// to `invoke-restart` while it runs. Invoking one unwinds back here and runs its body, whose value
+/// becomes the value of the `restart-case`.
+pub fn restart_case(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let (form, clauses) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity("restart-case", "at least 1", 0)),
+    };
+
+    let restarts = clauses.iter().map(|clause| to_restart(env.clone(), clause)).collect::<Result<Vec<_>, _>>()?;
+    let names = restarts.iter().map(|restart| restart.name.clone()).collect();
+    let restart_env = env.clone();
+
+    Ok(LispStep::Try(
+        Box::new(LispStep::TailCall(env, form.clone())),
+        LispCatches::Restarts(names),
+        Rc::new(move |err| match err.kind {
+            LispErrorKind::RestartInvoked {
+                ref name,
+                ref args,
+            } => match restarts.iter().find(|restart| restart.name == *name) {
+                Some(restart) => restart.func_executor.exec_step(restart_env.clone(), args),
+                None => Err(err.clone()),
+            },
+            _ => Err(err.clone()),
+        }),
+    ))
+}
+
+/// Unwinds to the innermost active restart named `name`, passing it the rest of the args.
+pub fn invoke_restart(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let (name, restart_args) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity("invoke-restart", "at least 1", 0)),
+    };
+
+    match *name.borrow() {
+        LispCell::Atom(ref name) => Err(LispError::restart_invoked(name, restart_args.to_vec())),
+        _ => Err(LispError::type_error("restart name", name)),
+    }
+}
+
+fn handle_from(
+    env: Rc<RefCell<Environment>>,
+    err: LispError,
+    selectors: Rc<Vec<Selector>>,
+    handlers: Rc<Vec<LispCellRef>>,
+    index: usize,
+) -> Result<LispStep, LispError> {
+    let error_cell = match selectors.get(index) {
+        Some(_) => LispCell::Error(err.clone()).to_ref(),
+        None => return Ok(LispStep::Done(core::lisp_null())),
+    };
+
+    let handler_env = env.clone();
+
+    selectors[index].test(env, &err, &error_cell)?.and_then(move |is_match| {
+        let call = LispCell::new_list(vec![handlers[index].clone(), error_cell.clone()]);
+        let (next_env, err, selectors, handlers) =
+            (handler_env.clone(), err.clone(), selectors.clone(), handlers.clone());
+        let next = move |_| handle_from(next_env.clone(), err.clone(), selectors.clone(), handlers.clone(), index + 1);
+
+        match is_match.borrow().is_truthy() {
+            true => Ok(LispStep::eval_then(handler_env.clone(), call, next)),
+            false => next(core::lisp_null()),
+        }
+    })
+}
+
+fn to_handler_binding(binding: &LispCellRef) -> Result<(Selector, LispCellRef), LispError> {
+    if let LispCell::List(ref parts) = *binding.borrow() {
+        if let [selector, handler] = LispList::to_vec(parts.clone()).as_slice() {
+            return Ok((Selector::parse(selector), handler.clone()));
+        }
+    }
+
+    Err(LispError::syntax("handler-bind handlers must be (selector handler) pairs").with_form(binding))
+}
+
+fn to_restart(env: Rc<RefCell<Environment>>, clause: &LispCellRef) -> Result<LispFunc, LispError> {
+    let parts = match *clause.borrow() {
+        LispCell::List(ref parts) => LispList::to_vec(parts.clone()),
+        _ => vec![],
+    };
+
+    match parts.as_slice() {
+        [name, params, body @ ..] => match *name.borrow() {
+            LispCell::Atom(ref name) => make_func(name.clone(), LispFuncType::Normal, env, params, body),
+            _ => Err(LispError::type_error("restart name", name)),
+        },
+        _ => Err(LispError::syntax("restarts must be (name (params...) body...)").with_form(clause)),
+    }
+}
@@ -0,0 +1,47 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::{dew, Environment, LispCell, LispCellRef, LispError, LispStep, Pattern};
+
+/// Calls `f` with the current continuation: a function that, called with a value, abandons
+/// whatever evaluation is doing at the time and carries on from where `call/cc` returned, with
+/// the value as its result. It can be called any number of times, even after `call/cc` has
+/// returned, though only as far as the end of the top-level form it was captured in. Jumping to a
+/// continuation doesn't run the `finally` clauses of the `try`s it leaves.
+pub fn call_cc(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let f = match args {
+        [f] => match *f.borrow() {
+            LispCell::Func(_) => f.clone(),
+            _ => return Err(LispError::type_error("func", f)),
+        },
+        _ => return Err(LispError::arity("call/cc", "1", args.len())),
+    };
+
+    Ok(LispStep::CallCC(Rc::new(move |continuation| {
+        Ok(LispStep::TailCall(env.clone(), LispCell::new_list(vec![f.clone(), continuation])))
+    })))
+}
+
+/// Runs the body, delimiting the continuations captured by `shift` within it.
+pub fn reset(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    Ok(LispStep::Reset(Box::new(dew(env, args)?)))
+}
+
+/// Abandons evaluation up to the innermost `reset`, binding the name to a function that carries on
+/// from here to the end of that `reset` and returns its value. The body is then run in place of
+/// the `reset`.
+pub fn shift(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let (name, body) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity("shift", "at least 1", 0)),
+    };
+
+    let (pattern, body) = (Pattern::parse(name)?, body.to_vec());
+
+    Ok(LispStep::Shift(Rc::new(move |continuation| {
+        let mut frame = Environment::new_child(env.clone());
+        pattern.bind(&continuation, &mut frame)?;
+
+        dew(Rc::new(RefCell::new(frame)), &body)
+    })))
+}
@@ -0,0 +1,151 @@
+use print::print_cell;
+
+use super::{Environment, LispCell, LispCellRef, LispError, LispList};
+
+/// Where a value gets bound by a parameter or a `let`: a symbol, `_` to ignore it, or a list of
+/// patterns to bind the elements of a list to, optionally ending in `&rest pattern` (or
+/// `. pattern`) for whatever elements are left over.
+///
+/// Patterns can be nested as deeply as the lists they're written as, so rather than a tree that
+/// would be built, walked and dropped recursively, the parts of a pattern are kept in one flat list
+/// that list parts refer into, with the whole pattern first.
+#[derive(Clone)]
+pub struct Pattern {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone)]
+enum Part {
+    Ignore,
+    Symbol(String),
+    List {
+        /// The indices of the item patterns in `parts`
+        items: Vec<usize>,
+        rest: Option<usize>,
+        /// The pattern as written, which is only printed for error messages
+        written: LispCellRef,
+    },
+}
+
+impl Pattern {
+    pub fn parse(cell: &LispCellRef) -> Result<Pattern, LispError> {
+        let mut parts = vec![Part::Ignore];
+        let mut pending = vec![(0, cell.clone())];
+
+        while let Some((index, cell)) = pending.pop() {
+            let cells = match *cell.borrow() {
+                LispCell::Atom(ref name) if name == "_" => continue,
+                LispCell::Atom(ref name) => {
+                    parts[index] = Part::Symbol(name.clone());
+                    continue;
+                }
+                LispCell::List(ref list) => LispList::to_vec(list.clone()),
+                _ => return Err(LispError::type_error("symbol or list to bind", &cell)),
+            };
+
+            let rest_index = cells.iter().position(|cell| match *cell.borrow() {
+                LispCell::Atom(ref name) => name == "&rest" || name == ".",
+                _ => false,
+            });
+
+            let (items, rest) = match rest_index {
+                None => (&cells[..], None),
+                Some(index) if index + 2 == cells.len() => (&cells[..index], Some(&cells[index + 1])),
+                Some(index) => {
+                    let msg = "&rest must be followed by exactly one pattern";
+
+                    return Err(LispError::syntax(msg).with_form(&cells[index]));
+                }
+            };
+
+            // Each part gets its place now and is filled in when it comes off the stack, which it
+            // does in the order it was written
+            let targets = items.iter().chain(rest).cloned().collect::<Vec<_>>();
+            let start = parts.len();
+
+            parts.extend(targets.iter().map(|_| Part::Ignore));
+            pending.extend((start..parts.len()).zip(targets).rev());
+
+            parts[index] = Part::List {
+                items: (start..start + items.len()).collect(),
+                rest: rest.map(|_| start + items.len()),
+                written: cell.clone(),
+            };
+        }
+
+        Ok(Pattern {
+            parts,
+        })
+    }
+
+    pub fn symbol(name: String) -> Pattern {
+        Pattern {
+            parts: vec![Part::Symbol(name)],
+        }
+    }
+
+    /// The pattern as it was written.
+    pub fn to_cell(&self) -> LispCellRef {
+        match self.parts[0] {
+            Part::Ignore => LispCell::Atom("_".to_string()).to_ref(),
+            Part::Symbol(ref name) => LispCell::Atom(name.clone()).to_ref(),
+            Part::List {
+                ref written, ..
+            } => written.clone(),
+        }
+    }
+
+    /// Binds the parts of `value` to the symbols in this pattern in `frame`, or fails if `value`
+    /// doesn't have the pattern's shape.
+    pub fn bind(&self, value: &LispCellRef, frame: &mut Environment) -> Result<(), LispError> {
+        let mut pending = vec![(0, value.clone())];
+
+        while let Some((index, value)) = pending.pop() {
+            let (items, rest, written) = match self.parts[index] {
+                Part::Ignore => continue,
+                Part::Symbol(ref name) => {
+                    frame.def(name.clone(), value);
+                    continue;
+                }
+                Part::List {
+                    ref items,
+                    ref rest,
+                    ref written,
+                } => (items, rest, written),
+            };
+
+            let values = match *value.borrow() {
+                LispCell::List(ref list) => Some(LispList::to_vec(list.clone())),
+                _ => None,
+            };
+
+            let values = match values {
+                Some(values) if values.len() == items.len() || rest.is_some() && values.len() > items.len() => values,
+                _ => {
+                    let expected = format!("list matching {}", print_cell(written.clone()));
+
+                    return Err(LispError::type_error(&expected, &value));
+                }
+            };
+
+            // The parts are bound from the top of the stack, so they go on last to first
+            if let Some(rest) = *rest {
+                pending.push((rest, LispCell::new_list(values[items.len()..].to_vec())));
+            }
+
+            pending.extend(items.iter().cloned().zip(values).rev());
+        }
+
+        Ok(())
+    }
+
+    /// The symbols this pattern binds.
+    pub fn names(&self) -> Vec<String> {
+        self.parts
+            .iter()
+            .filter_map(|part| match *part {
+                Part::Symbol(ref name) => Some(name.clone()),
+                _ => None,
+            }).collect()
+    }
+}
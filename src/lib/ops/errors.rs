@@ -0,0 +1,275 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use print::print_cell;
+
+use super::core;
+use super::{
+    dew, Environment, LispCatches, LispCell, LispCellRef, LispError, LispErrorKind, LispList, LispNumber, LispResult,
+    LispStep, Pattern,
+};
+
+/// Raises `value` as an error. Throwing an error object caught by `try` raises it again as it was.
+pub fn throw(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [value] => match *value.borrow() {
+            LispCell::Error(ref err) => Err(err.clone()),
+            _ => Err(LispError::user(value.clone())),
+        },
+        _ => Err(LispError::arity("throw", "1", args.len())),
+    }
+}
+
+/// Raises an error with a message made of a string followed by any other args, printed.
+pub fn error(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let (msg, irritants) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity("error", "at least 1", 0)),
+    };
+
+    let mut msg = match *msg.borrow() {
+        LispCell::Str(ref msg) => msg.clone(),
+        _ => return Err(LispError::type_error("string", msg)),
+    };
+
+    for irritant in irritants {
+        msg.push(' ');
+        msg.push_str(print_cell(irritant.clone()).as_str());
+    }
+
+    Err(LispError::user(LispCell::Str(msg).to_ref()))
+}
+
+/// Runs the body, handing any error it raises to the first `(catch selector name handler...)`
+/// clause whose selector matches, with the error bound to `name`. A selector is the keyword for a
+/// kind of error, such as `:type` or `:user`, `:any`, or an expression for a predicate that's
+/// called with the error. The body of a `(finally cleanup...)` clause at the end is run however
+/// the rest of the `try` finishes, without changing its value.
+pub fn try_op(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    let mut body = vec![];
+    let mut clauses = vec![];
+    let mut cleanup = None;
+
+    for arg in args.iter() {
+        if cleanup.is_some() {
+            return Err(LispError::syntax("finally must be the last clause of a try").with_form(arg));
+        }
+
+        match clause_parts(arg) {
+            Some((ref name, ref parts)) if name == "catch" => clauses.push(to_catch_clause(arg, parts)?),
+            Some((ref name, ref parts)) if name == "finally" => cleanup = Some(parts.clone()),
+            _ if !clauses.is_empty() => {
+                return Err(LispError::syntax("a try's body must come before its catch clauses").with_form(arg))
+            }
+            _ => body.push(arg.clone()),
+        }
+    }
+
+    let body = dew(env.clone(), &body)?;
+
+    let caught = match clauses.is_empty() {
+        true => body,
+        false => {
+            let (handler_env, clauses) = (env.clone(), Rc::new(clauses));
+
+            LispStep::try_catch(body, move |err| catch_from(handler_env.clone(), err, clauses.clone(), 0))
+        }
+    };
+
+    match cleanup {
+        Some(cleanup) => with_cleanup(env, caught, Rc::new(cleanup)),
+        None => Ok(caught),
+    }
+}
+
+pub fn is_error(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [value] => match *value.borrow() {
+            LispCell::Error(_) => Ok(LispCell::Bool(true).to_ref()),
+            _ => Ok(LispCell::Bool(false).to_ref()),
+        },
+        _ => Err(LispError::arity("error?", "1", args.len())),
+    }
+}
+
+/// The message given to `error`, the printed value given to `throw`, or the description of a
+/// built-in error.
+pub fn error_message(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let err = to_error("error-message", args)?;
+
+    let msg = match err.kind {
+        LispErrorKind::User(ref value) => match *value.borrow() {
+            LispCell::Str(ref msg) => msg.clone(),
+            _ => print_cell(value.clone()),
+        },
+        ref kind => kind.to_string(),
+    };
+
+    Ok(LispCell::Str(msg).to_ref())
+}
+
+/// The keyword `catch` selects the error by, such as `:unbound-symbol`.
+pub fn error_kind(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let err = to_error("error-kind", args)?;
+
+    Ok(LispCell::Atom(format!(":{}", err.kind.name())).to_ref())
+}
+
+/// The `(line col)` the error was raised at, or nil if it isn't known.
+pub fn error_span(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let err = to_error("error-span", args)?;
+
+    match err.span {
+        Some(span) => Ok(LispCell::new_list(vec![
+            LispCell::Number(LispNumber::Int(span.line as i64)).to_ref(),
+            LispCell::Number(LispNumber::Int(span.col as i64)).to_ref(),
+        ])),
+        None => Ok(core::lisp_null()),
+    }
+}
+
+/// The value given to `throw`, or nil for errors raised any other way.
+pub fn error_value(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let err = to_error("error-value", args)?;
+
+    match err.kind {
+        LispErrorKind::User(ref value) => Ok(value.clone()),
+        _ => Ok(core::lisp_null()),
+    }
+}
+
+struct CatchClause {
+    selector: Selector,
+    name: Pattern,
+    body: Vec<LispCellRef>,
+}
+
+/// Which errors a `catch` clause or `handler-bind` handler applies to: those of a kind such as
+/// `:type`, `:any` error, or those a predicate holds for.
+pub enum Selector {
+    Any,
+    Kind(String),
+    Predicate(LispCellRef),
+}
+
+impl Selector {
+    pub fn parse(selector: &LispCellRef) -> Selector {
+        match *selector.borrow() {
+            LispCell::Atom(ref kind) if kind == ":any" => Selector::Any,
+            LispCell::Atom(ref kind) if kind.len() > 1 && kind.starts_with(':') => Selector::Kind(kind[1..].to_string()),
+            _ => Selector::Predicate(selector.clone()),
+        }
+    }
+
+    /// A step whose value is truthy if this selects `err`, which is also given as `error_cell` to
+    /// pass to predicates.
+    pub fn test(
+        &self,
+        env: Rc<RefCell<Environment>>,
+        err: &LispError,
+        error_cell: &LispCellRef,
+    ) -> Result<LispStep, LispError> {
+        let is_match = match *self {
+            Selector::Any => true,
+            Selector::Kind(ref kind) => kind == err.kind.name(),
+            Selector::Predicate(ref predicate) => {
+                let call = LispCell::new_list(vec![predicate.clone(), error_cell.clone()]);
+
+                return Ok(LispStep::TailCall(env, call));
+            }
+        };
+
+        Ok(LispStep::Done(LispCell::Bool(is_match).to_ref()))
+    }
+}
+
+fn catch_from(
+    env: Rc<RefCell<Environment>>,
+    err: LispError,
+    clauses: Rc<Vec<CatchClause>>,
+    index: usize,
+) -> Result<LispStep, LispError> {
+    let error_cell = match clauses.get(index) {
+        Some(_) => LispCell::Error(err.clone()).to_ref(),
+        None => return Err(err),
+    };
+
+    let handler_env = env.clone();
+
+    clauses[index].selector.test(env, &err, &error_cell)?.and_then(move |is_match| {
+        match is_match.borrow().is_truthy() {
+            true => handle(handler_env.clone(), &error_cell, &clauses[index]),
+            false => catch_from(handler_env.clone(), err.clone(), clauses.clone(), index + 1),
+        }
+    })
+}
+
+fn handle(
+    env: Rc<RefCell<Environment>>,
+    error_cell: &LispCellRef,
+    clause: &CatchClause,
+) -> Result<LispStep, LispError> {
+    let mut frame = Environment::new_child(env);
+    clause.name.bind(error_cell, &mut frame)?;
+
+    dew(Rc::new(RefCell::new(frame)), &clause.body)
+}
+
+/// Runs `cleanup` once `step` has finished, whether with a value, an error or by invoking a
+/// restart, then finishes the same way `step` did.
+fn with_cleanup(
+    env: Rc<RefCell<Environment>>,
+    step: LispStep,
+    cleanup: Rc<Vec<LispCellRef>>,
+) -> Result<LispStep, LispError> {
+    let (err_env, err_cleanup) = (env.clone(), cleanup.clone());
+
+    let guarded = LispStep::Try(
+        Box::new(step),
+        LispCatches::Unwinding,
+        Rc::new(move |err| dew(err_env.clone(), &err_cleanup)?.and_then(move |_| Err(err.clone()))),
+    );
+
+    guarded.and_then(move |value| dew(env.clone(), &cleanup)?.and_then(move |_| Ok(LispStep::Done(value.clone()))))
+}
+
+/// The name and the rest of a clause like `(catch ...)`, if `cell` looks like one.
+fn clause_parts(cell: &LispCellRef) -> Option<(String, Vec<LispCellRef>)> {
+    let parts = match *cell.borrow() {
+        LispCell::List(ref parts) => LispList::to_vec(parts.clone()),
+        _ => return None,
+    };
+
+    let name = match parts.first() {
+        Some(first) => match *first.borrow() {
+            LispCell::Atom(ref name) => name.clone(),
+            _ => return None,
+        },
+        None => return None,
+    };
+
+    Some((name, parts[1..].to_vec()))
+}
+
+fn to_catch_clause(clause: &LispCellRef, parts: &[LispCellRef]) -> Result<CatchClause, LispError> {
+    let (selector, name) = match parts {
+        [selector, name, ..] => (selector, name),
+        _ => return Err(LispError::syntax("catch clauses must be (catch selector name handler...)").with_form(clause)),
+    };
+
+    Ok(CatchClause {
+        selector: Selector::parse(selector),
+        name: Pattern::parse(name)?,
+        body: parts[2..].to_vec(),
+    })
+}
+
+fn to_error(name: &str, args: &[LispCellRef]) -> Result<LispError, LispError> {
+    match args {
+        [value] => match *value.borrow() {
+            LispCell::Error(ref err) => Ok(err.clone()),
+            _ => Err(LispError::type_error("error", value)),
+        },
+        _ => Err(LispError::arity(name, "1", args.len())),
+    }
+}
@@ -0,0 +1,79 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::core;
+use super::{dew, Environment, LispCell, LispCellRef, LispError, LispResult, LispStep};
+
+pub fn and(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    match args.is_empty() {
+        true => Ok(LispStep::Done(LispCell::Bool(true).to_ref())),
+        false => short_circuit(env, Rc::new(args.to_vec()), 0, false),
+    }
+}
+
+pub fn or(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    match args.is_empty() {
+        true => Ok(LispStep::Done(LispCell::Bool(false).to_ref())),
+        false => short_circuit(env, Rc::new(args.to_vec()), 0, true),
+    }
+}
+
+pub fn not(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [arg] => Ok(LispCell::Bool(!arg.borrow().is_truthy()).to_ref()),
+        _ => Err(LispError::arity("not", "1", args.len())),
+    }
+}
+
+pub fn when(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    conditional_body("when", env, args, true)
+}
+
+pub fn unless(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    conditional_body("unless", env, args, false)
+}
+
+/// Evaluates `args` from `index` until one's truthiness is `stop_when`, and returns that value
+/// without evaluating the rest. The last arg is left in tail position.
+fn short_circuit(
+    env: Rc<RefCell<Environment>>,
+    args: Rc<Vec<LispCellRef>>,
+    index: usize,
+    stop_when: bool,
+) -> Result<LispStep, LispError> {
+    if index == args.len() - 1 {
+        return Ok(LispStep::TailCall(env, args[index].clone()));
+    }
+
+    let (rest_env, cell) = (env.clone(), args[index].clone());
+
+    Ok(LispStep::eval_then(env, cell, move |value| {
+        match value.borrow().is_truthy() == stop_when {
+            true => Ok(LispStep::Done(value.clone())),
+            false => short_circuit(rest_env.clone(), args.clone(), index + 1, stop_when),
+        }
+    }))
+}
+
+/// Runs the body after the predicate in `args` when the predicate's truthiness is `run_when`, and
+/// otherwise returns nil.
+fn conditional_body(
+    name: &str,
+    env: Rc<RefCell<Environment>>,
+    args: &[LispCellRef],
+    run_when: bool,
+) -> Result<LispStep, LispError> {
+    let (pred, body) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(LispError::arity(name, "at least 1", 0)),
+    };
+
+    let (body_env, body) = (env.clone(), body.to_vec());
+
+    Ok(LispStep::eval_then(env, pred.clone(), move |pred_result| {
+        match pred_result.borrow().is_truthy() == run_when {
+            true => dew(body_env.clone(), &body),
+            false => Ok(LispStep::Done(core::lisp_null())),
+        }
+    }))
+}
@@ -0,0 +1,283 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use super::{Environment, LispCell, LispCellRef, LispError, LispNumber, LispResult};
+
+pub fn add(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let sum = to_nums(args)?.iter().try_fold(LispNumber::Int(0), |acc, val| acc.add(val))?;
+
+    Ok(LispCell::Number(sum).to_ref())
+}
+
+/// Subtracts the rest of the args from the first, or negates a single arg.
+pub fn sub(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let mut nums = to_nums(args)?.into_iter();
+    let first = match (nums.next(), args.len()) {
+        (Some(first), 1) => first.neg(),
+        (Some(first), _) => first,
+        (None, _) => return Err(LispError::arity("-", "at least 1", 0)),
+    };
+
+    Ok(LispCell::Number(nums.try_fold(first, |acc, val| acc.sub(&val))?).to_ref())
+}
+
+pub fn mul(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let product = to_nums(args)?.iter().try_fold(LispNumber::Int(1), |acc, val| acc.mul(val))?;
+
+    Ok(LispCell::Number(product).to_ref())
+}
+
+/// Divides the first arg by the rest in turn, or gives the reciprocal of a single arg.
+pub fn div(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let mut nums = to_nums(args)?.into_iter();
+    let first = match (nums.next(), args.len()) {
+        (Some(first), 1) => LispNumber::Int(1).div(&first)?,
+        (Some(first), _) => first,
+        (None, _) => return Err(LispError::arity("/", "at least 1", 0)),
+    };
+
+    let quotient = nums.try_fold(first, |acc, val| acc.div(&val))?;
+
+    Ok(LispCell::Number(quotient).to_ref())
+}
+
+pub fn num_eq(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    compare_chain("=", args, |ordering| ordering == Ordering::Equal)
+}
+
+pub fn lt(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    compare_chain("<", args, |ordering| ordering == Ordering::Less)
+}
+
+pub fn gt(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    compare_chain(">", args, |ordering| ordering == Ordering::Greater)
+}
+
+pub fn lte(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    compare_chain("<=", args, |ordering| ordering != Ordering::Greater)
+}
+
+pub fn gte(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    compare_chain(">=", args, |ordering| ordering != Ordering::Less)
+}
+
+pub fn quot(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let (a, b) = to_integer_pair("quot", args)?;
+
+    Ok(LispCell::Number(a.div_rem(&b)?.0).to_ref())
+}
+
+pub fn rem(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let (a, b) = to_integer_pair("rem", args)?;
+
+    Ok(LispCell::Number(a.div_rem(&b)?.1).to_ref())
+}
+
+pub fn modulo(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let (a, b) = to_integer_pair("mod", args)?;
+
+    Ok(LispCell::Number(a.modulo(&b)?).to_ref())
+}
+
+pub fn abs(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    Ok(LispCell::Number(to_single_num("abs", args)?.abs()).to_ref())
+}
+
+pub fn min(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    extremum("min", args, Ordering::Less)
+}
+
+pub fn max(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    extremum("max", args, Ordering::Greater)
+}
+
+pub fn floor(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    Ok(LispCell::Number(to_single_num("floor", args)?.floor()).to_ref())
+}
+
+pub fn ceil(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    Ok(LispCell::Number(to_single_num("ceil", args)?.ceil()).to_ref())
+}
+
+pub fn round(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    Ok(LispCell::Number(to_single_num("round", args)?.round()).to_ref())
+}
+
+pub fn sqrt(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    Ok(LispCell::Number(to_single_num("sqrt", args)?.sqrt()).to_ref())
+}
+
+pub fn expt(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [base, exponent] => Ok(LispCell::Number(to_num(base)?.expt(&to_num(exponent)?)?).to_ref()),
+        _ => Err(LispError::arity("expt", "2", args.len())),
+    }
+}
+
+pub fn exp(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    float_fn("exp", args, f64::exp)
+}
+
+/// The natural log of a number, or its log in the base given as an optional second arg.
+pub fn logarithm(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [num] => Ok(LispCell::Number(LispNumber::Float(to_num(num)?.to_f64().ln())).to_ref()),
+        [num, base] => {
+            let log = to_num(num)?.to_f64().ln() / to_num(base)?.to_f64().ln();
+
+            Ok(LispCell::Number(LispNumber::Float(log)).to_ref())
+        }
+        _ => Err(LispError::arity("log", "1 or 2", args.len())),
+    }
+}
+
+pub fn sin(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    float_fn("sin", args, f64::sin)
+}
+
+pub fn cos(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    float_fn("cos", args, f64::cos)
+}
+
+pub fn tan(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    float_fn("tan", args, f64::tan)
+}
+
+pub fn asin(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    float_fn("asin", args, f64::asin)
+}
+
+pub fn acos(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    float_fn("acos", args, f64::acos)
+}
+
+/// The arc tangent of a number, or with two args `y` and `x`, the angle of the point `(x, y)`.
+pub fn atan(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [num] => Ok(LispCell::Number(LispNumber::Float(to_num(num)?.to_f64().atan())).to_ref()),
+        [y, x] => {
+            let angle = to_num(y)?.to_f64().atan2(to_num(x)?.to_f64());
+
+            Ok(LispCell::Number(LispNumber::Float(angle)).to_ref())
+        }
+        _ => Err(LispError::arity("atan", "1 or 2", args.len())),
+    }
+}
+
+pub fn is_number(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    num_predicate("number?", args, |_| true)
+}
+
+pub fn is_integer(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    num_predicate("integer?", args, LispNumber::is_integer)
+}
+
+pub fn is_rational(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    num_predicate("rational?", args, |num| num.is_exact() || num.to_f64().is_finite())
+}
+
+pub fn is_float(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    num_predicate("float?", args, |num| !num.is_exact())
+}
+
+fn compare_chain<F>(name: &str, args: &[LispCellRef], accept: F) -> LispResult
+where
+    F: Fn(Ordering) -> bool,
+{
+    if args.is_empty() {
+        return Err(LispError::arity(name, "at least 1", 0));
+    }
+
+    let nums = to_nums(args)?;
+    for pair in nums.windows(2) {
+        if !pair[0].compare(&pair[1])?.is_some_and(&accept) {
+            return Ok(LispCell::Bool(false).to_ref());
+        }
+    }
+
+    Ok(LispCell::Bool(true).to_ref())
+}
+
+/// Finds the number that compares as `keep` against all the others. If any arg is a float, so is
+/// the result.
+fn extremum(name: &str, args: &[LispCellRef], keep: Ordering) -> LispResult {
+    let mut nums = to_nums(args)?.into_iter();
+    let first = match nums.next() {
+        Some(first) => first,
+        None => return Err(LispError::arity(name, "at least 1", 0)),
+    };
+
+    let mut is_exact = first.is_exact();
+    let mut best = first;
+
+    for num in nums {
+        is_exact = is_exact && num.is_exact();
+
+        match num.compare(&best)? {
+            Some(ordering) if ordering == keep => best = num,
+            None => best = LispNumber::Float(f64::NAN),
+            _ => {}
+        }
+    }
+
+    match is_exact {
+        true => Ok(LispCell::Number(best).to_ref()),
+        false => Ok(LispCell::Number(best.to_float()).to_ref()),
+    }
+}
+
+fn float_fn(name: &str, args: &[LispCellRef], f: fn(f64) -> f64) -> LispResult {
+    let num = to_single_num(name, args)?;
+
+    Ok(LispCell::Number(LispNumber::Float(f(num.to_f64()))).to_ref())
+}
+
+fn num_predicate<F>(name: &str, args: &[LispCellRef], predicate: F) -> LispResult
+where
+    F: Fn(&LispNumber) -> bool,
+{
+    match args {
+        [arg] => {
+            let result = match *arg.borrow() {
+                LispCell::Number(ref num) => predicate(num),
+                _ => false,
+            };
+
+            Ok(LispCell::Bool(result).to_ref())
+        }
+        _ => Err(LispError::arity(name, "1", args.len())),
+    }
+}
+
+fn to_num(arg: &LispCellRef) -> Result<LispNumber, LispError> {
+    match *arg.borrow() {
+        LispCell::Number(ref num) => Ok(num.clone()),
+        _ => Err(LispError::type_error("number", arg)),
+    }
+}
+
+fn to_nums(args: &[LispCellRef]) -> Result<Vec<LispNumber>, LispError> {
+    args.iter().map(to_num).collect()
+}
+
+fn to_single_num(name: &str, args: &[LispCellRef]) -> Result<LispNumber, LispError> {
+    match args {
+        [arg] => to_num(arg),
+        _ => Err(LispError::arity(name, "1", args.len())),
+    }
+}
+
+fn to_integer(arg: &LispCellRef) -> Result<LispNumber, LispError> {
+    match *arg.borrow() {
+        LispCell::Number(ref num) if num.is_integer() => Ok(num.clone()),
+        _ => Err(LispError::type_error("integer", arg)),
+    }
+}
+
+fn to_integer_pair(name: &str, args: &[LispCellRef]) -> Result<(LispNumber, LispNumber), LispError> {
+    match args {
+        [a, b] => Ok((to_integer(a)?, to_integer(b)?)),
+        _ => Err(LispError::arity(name, "2", args.len())),
+    }
+}
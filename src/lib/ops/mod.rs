@@ -0,0 +1,334 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::core::{self, log};
+use super::{
+    eval_body, eval_each, macroexpand, macroexpand_1, run_step, Environment, LispCatches, LispCell, LispCellRef,
+    LispError, LispErrorKind, LispFunc, LispFuncExecutor, LispFuncType, LispList, LispNumber, LispResult, LispStep,
+};
+
+mod binding;
+mod branch;
+mod conditions;
+mod continuations;
+mod destructure;
+mod errors;
+mod logic;
+mod math;
+mod params;
+
+pub use self::binding::*;
+pub use self::branch::*;
+pub use self::conditions::*;
+pub use self::continuations::*;
+pub use self::errors::*;
+pub use self::logic::*;
+pub use self::math::*;
+
+use self::destructure::Pattern;
+use self::params::ParamList;
+
+pub fn list(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    Ok(LispCell::new_list(args.to_vec()))
+}
+
+pub fn def(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    define("def", env, args, false)
+}
+
+/// Like `def`, but leaves the symbol alone, without evaluating the value, if it's already defined
+/// at top level.
+pub fn defonce(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    define("defonce", env, args, true)
+}
+
+/// Evaluates the value in the current environment and binds it to the symbol in the top-level one,
+/// so definitions inside function bodies and `let`s are visible everywhere.
+fn define(
+    name: &str,
+    env: Rc<RefCell<Environment>>,
+    args: &[LispCellRef],
+    once: bool,
+) -> Result<LispStep, LispError> {
+    match args {
+        [cell, value] => match *cell.borrow() {
+            LispCell::Atom(ref symbol) => {
+                let root = Environment::root(env.clone());
+
+                if once && root.borrow().symbols.contains_key(symbol) {
+                    return Ok(LispStep::Done(cell.clone()));
+                }
+
+                let (symbol, cell) = (symbol.clone(), cell.clone());
+
+                Ok(LispStep::eval_then(env, value.clone(), move |value| {
+                    log(|| println!("Defining symbol: {:?} with value: {:?}", symbol, value));
+
+                    root.borrow_mut().def(symbol.clone(), value);
+
+                    log(|| println!("Symbol {:?} defined", symbol));
+
+                    Ok(LispStep::Done(cell.clone()))
+                }))
+            }
+            _ => Err(LispError::type_error("symbol to define", cell)),
+        },
+        _ => Err(LispError::arity(name, "2", args.len())),
+    }
+}
+
+/// Evaluates the value and rebinds the nearest existing binding of the symbol to it.
+pub fn set(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    match args {
+        [cell, value] => match *cell.borrow() {
+            LispCell::Atom(ref symbol) => {
+                let (set_env, symbol) = (env.clone(), symbol.clone());
+
+                Ok(LispStep::eval_then(env, value.clone(), move |value| {
+                    match set_env.borrow_mut().set_sym(&symbol, value.clone()) {
+                        true => Ok(LispStep::Done(value)),
+                        false => Err(LispError::unbound_symbol(&symbol)),
+                    }
+                }))
+            }
+            _ => Err(LispError::type_error("symbol to set", cell)),
+        },
+        _ => Err(LispError::arity("set!", "2", args.len())),
+    }
+}
+
+pub fn defn(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    define_func("defn", LispFuncType::Normal, env, args)
+}
+
+pub fn defmacro(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    define_func("defmacro", LispFuncType::Macro, env, args)
+}
+
+/// Defines a function or macro at top level from its name, parameter list and body.
+fn define_func(
+    op_name: &str,
+    func_type: LispFuncType,
+    env: Rc<RefCell<Environment>>,
+    args: &[LispCellRef],
+) -> LispResult {
+    if args.len() < 3 {
+        return Err(LispError::arity(op_name, "at least 3", args.len()));
+    }
+
+    let func_name = match *args[0].borrow() {
+        LispCell::Atom(ref func_name) => func_name.clone(),
+        _ if func_type == LispFuncType::Macro => return Err(LispError::type_error("symbol to name macro", &args[0])),
+        _ => return Err(LispError::type_error("symbol to name func", &args[0])),
+    };
+
+    log(|| println!("preparing to {} {}", op_name, func_name));
+
+    let func = make_func(func_name.clone(), func_type, env.clone(), &args[1], &args[2..])?;
+    let func = LispCell::Func(func).to_ref();
+
+    Environment::root(env).borrow_mut().def(func_name, func.clone());
+
+    Ok(func)
+}
+
+pub fn macroexpand_op(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    match args {
+        [form] => macroexpand(env, form.clone()),
+        _ => Err(LispError::arity("macroexpand", "1", args.len())),
+    }
+}
+
+pub fn macroexpand_1_op(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    match args {
+        [form] => Ok(macroexpand_1(env, form)?.unwrap_or_else(|| LispStep::Done(form.clone()))),
+        _ => Err(LispError::arity("macroexpand-1", "1", args.len())),
+    }
+}
+
+static GENSYM_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub fn gensym(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    let prefix = match args {
+        [] => String::from("G"),
+        [prefix] => match *prefix.borrow() {
+            LispCell::Str(ref prefix) | LispCell::Atom(ref prefix) => prefix.clone(),
+            _ => return Err(LispError::type_error("string or symbol as gensym prefix", prefix)),
+        },
+        _ => return Err(LispError::arity("gensym", "0 or 1", args.len())),
+    };
+
+    let n = GENSYM_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    Ok(LispCell::Atom(format!("{}__{}", prefix, n)).to_ref())
+}
+
+pub fn dew(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    // Execute each arg in the vec, leaving the last one in tail position
+    match args {
+        [] => Ok(LispStep::Done(core::lisp_null())),
+        [only] => Ok(LispStep::TailCall(env, only.clone())),
+        _ => eval_body(env, Rc::new(args.to_vec())),
+    }
+}
+
+pub fn push(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [el, list_arg] => match *list_arg.borrow_mut() {
+            LispCell::List(ref list) => {
+                list.borrow_mut().push_back(el.clone());
+
+                Ok(list_arg.clone())
+            }
+            _ => Err(LispError::type_error("list", list_arg)),
+        },
+        _ => Err(LispError::arity("push", "2", args.len())),
+    }
+}
+
+pub fn car(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [list_arg] => match *list_arg.borrow() {
+            LispCell::List(ref list) => match list.borrow().get_value() {
+                Some(value) => Ok(value),
+                _ => Ok(core::lisp_null()),
+            },
+            _ => Err(LispError::type_error("list", list_arg)),
+        },
+        _ => Err(LispError::arity("car", "1", args.len())),
+    }
+}
+
+pub fn cdr(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [list_arg] => match *list_arg.borrow() {
+            LispCell::List(ref list) => {
+                let (_, rest) = LispList::split(list.clone());
+
+                match rest {
+                    Some(rest) => Ok(LispCell::List(rest).to_ref()),
+                    None => Ok(core::lisp_null()),
+                }
+            }
+            _ => Err(LispError::type_error("list", list_arg)),
+        },
+        _ => Err(LispError::arity("cdr", "1", args.len())),
+    }
+}
+
+pub fn iff(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+    match args {
+        [pred, true_case, false_case] => {
+            let (branch_env, true_case, false_case) = (env.clone(), true_case.clone(), false_case.clone());
+
+            Ok(LispStep::eval_then(env, pred.clone(), move |pred_result| {
+                match pred_result.borrow().is_truthy() {
+                    true => Ok(LispStep::TailCall(branch_env.clone(), true_case.clone())),
+                    false => Ok(LispStep::TailCall(branch_env.clone(), false_case.clone())),
+                }
+            }))
+        }
+        _ => Err(LispError::arity("if", "3", args.len())),
+    }
+}
+
+pub fn eq(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [left, right] => {
+            let is_eq = left == right;
+
+            Ok(LispCell::Bool(is_eq).to_ref())
+        }
+        _ => Err(LispError::arity("eq", "2", args.len())),
+    }
+}
+
+pub fn lambda(env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args.split_first() {
+        Some((lambda_args, lambda_body)) if !lambda_body.is_empty() => {
+            log(|| println!("preparing to lambda {:?} {:?}", lambda_args, lambda_body));
+
+            let func = make_func(String::from("lambda"), LispFuncType::Normal, env, lambda_args, lambda_body)?;
+
+            Ok(LispCell::Func(func).to_ref())
+        }
+        _ => Err(LispError::arity("lambda", "at least 2", args.len())),
+    }
+}
+
+pub fn doc(_env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+    match args {
+        [func] => match *func.borrow() {
+            LispCell::Func(ref func) => match func.doc {
+                Some(ref doc) => Ok(LispCell::Str(doc.clone()).to_ref()),
+                None => Ok(core::lisp_null()),
+            },
+            _ => Err(LispError::type_error("func", func)),
+        },
+        _ => Err(LispError::arity("doc", "1", args.len())),
+    }
+}
+
+/// Makes a function that closes over `env` and runs each form of `body` in turn. A string at the
+/// start of a body with more forms after it is taken as the docstring.
+fn make_func(
+    name: String,
+    func_type: LispFuncType,
+    env: Rc<RefCell<Environment>>,
+    params: &LispCellRef,
+    body: &[LispCellRef],
+) -> Result<LispFunc, LispError> {
+    let params = ParamList::parse(params)?;
+
+    let doc = match body.split_first() {
+        Some((first, rest)) if !rest.is_empty() => match *first.borrow() {
+            LispCell::Str(ref doc) => Some(doc.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let body = match doc {
+        Some(_) => &body[1..],
+        None => body,
+    };
+
+    let func_executor = Box::new(DefnFuncExecutorImpl {
+        name: name.clone(),
+        params,
+        func_body: body.to_vec(),
+        env,
+    });
+
+    Ok(LispFunc::new(name, func_type, func_executor).with_doc(doc))
+}
+
+struct DefnFuncExecutorImpl {
+    name: String,
+    /// The forms run in order on each call, the last in tail position
+    func_body: Vec<LispCellRef>,
+    params: ParamList,
+    /// The environment the function was defined in, which each call's frame is a child of
+    env: Rc<RefCell<Environment>>,
+}
+
+impl LispFuncExecutor for DefnFuncExecutorImpl {
+    fn exec(&self, env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> LispResult {
+        run_step(self.exec_step(env, args)?)
+    }
+
+    fn exec_step(&self, _env: Rc<RefCell<Environment>>, args: &[LispCellRef]) -> Result<LispStep, LispError> {
+        log(|| println!("exec'ing {}", &self.name));
+
+        // Every call gets its own frame so recursive and re-entrant calls don't share parameters
+        let mut frame = Environment::new_child(self.env.clone());
+        let defaults = self.params.bind(&self.name, args, &mut frame)?;
+        let frame = Rc::new(RefCell::new(frame));
+
+        match defaults.is_empty() {
+            true => dew(frame, &self.func_body),
+            false => bind_in_place(frame, Rc::new(defaults), 0, Rc::new(self.func_body.clone())),
+        }
+    }
+}
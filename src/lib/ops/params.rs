@@ -0,0 +1,245 @@
+use print::print_cell;
+
+use super::core;
+use super::{Environment, LispCell, LispCellRef, LispError, LispList, Pattern};
+
+/// The parameters of a function, in the order `required &optional ... &rest name &key ...`. A
+/// dotted `. name` can stand in for `&rest name`, and optional and keyword parameters may be given
+/// as `(name default)` to evaluate `default` when they aren't passed. All but keyword parameters
+/// may be destructuring patterns.
+pub struct ParamList {
+    required: Vec<Pattern>,
+    optional: Vec<(Pattern, Option<LispCellRef>)>,
+    rest: Option<Pattern>,
+    keys: Vec<(String, Option<LispCellRef>)>,
+    /// The parameter list as written, which is only printed for error messages
+    written: LispCellRef,
+}
+
+#[derive(PartialEq, PartialOrd)]
+enum Section {
+    Required,
+    Optional,
+    Rest,
+    Key,
+}
+
+impl ParamList {
+    pub fn parse(params: &LispCellRef) -> Result<ParamList, LispError> {
+        let cells = match *params.borrow() {
+            LispCell::List(ref list) => LispList::to_vec(list.clone()),
+            _ => return Err(LispError::type_error("list of args", params)),
+        };
+
+        let mut param_list = ParamList {
+            required: vec![],
+            optional: vec![],
+            rest: None,
+            keys: vec![],
+            written: params.clone(),
+        };
+
+        let mut section = Section::Required;
+
+        for cell in cells.iter() {
+            let marker = match *cell.borrow() {
+                LispCell::Atom(ref name) if name == "&optional" => Some(Section::Optional),
+                LispCell::Atom(ref name) if name == "&rest" || name == "." => Some(Section::Rest),
+                LispCell::Atom(ref name) if name == "&key" => Some(Section::Key),
+                _ => None,
+            };
+
+            if let Some(marker) = marker {
+                let rest_is_unnamed = section == Section::Rest && param_list.rest.is_none();
+
+                if marker <= section || rest_is_unnamed {
+                    return Err(malformed(params));
+                }
+
+                section = marker;
+                continue;
+            }
+
+            match section {
+                Section::Required => param_list.required.push(Pattern::parse(cell)?),
+                Section::Optional => {
+                    let (target, default) = to_defaulted_param(cell)?;
+
+                    param_list.optional.push((Pattern::parse(&target)?, default));
+                }
+                Section::Rest if param_list.rest.is_none() => param_list.rest = Some(Pattern::parse(cell)?),
+                Section::Rest => return Err(malformed(params)),
+                Section::Key => {
+                    let (target, default) = to_defaulted_param(cell)?;
+
+                    param_list.keys.push((to_param_name(&target)?, default));
+                }
+            }
+        }
+
+        if section == Section::Rest && param_list.rest.is_none() {
+            return Err(malformed(params));
+        }
+
+        Ok(param_list)
+    }
+
+    /// A list of just required parameters.
+    pub fn from_patterns(patterns: Vec<Pattern>) -> ParamList {
+        let written = LispCell::new_list(patterns.iter().map(Pattern::to_cell).collect());
+
+        ParamList {
+            required: patterns,
+            optional: vec![],
+            rest: None,
+            keys: vec![],
+            written,
+        }
+    }
+
+    /// Binds `args` to the parameters in `frame`. Parameters that weren't passed are bound to nil,
+    /// and the ones of them with defaults are returned, in order, for the caller to evaluate.
+    pub fn bind(
+        &self,
+        name: &str,
+        args: &[LispCellRef],
+        frame: &mut Environment,
+    ) -> Result<Vec<(Pattern, LispCellRef)>, LispError> {
+        if args.len() < self.required.len() {
+            return Err(self.arity_error(name, args.len()));
+        }
+
+        let mut defaults = vec![];
+
+        for (param, arg) in self.required.iter().zip(args.iter()) {
+            param.bind(arg, frame)?;
+        }
+
+        // Optional parameters take positional args until the first keyword, if there are keyword
+        // parameters to pass it to
+        let mut index = self.required.len();
+        for (param, default) in self.optional.iter() {
+            let is_passed = match args.get(index) {
+                Some(arg) => self.keys.is_empty() || keyword_name(arg).is_none(),
+                None => false,
+            };
+
+            match is_passed {
+                true => {
+                    param.bind(&args[index], frame)?;
+                    index += 1;
+                }
+                false => Self::bind_missing(param, default, frame, &mut defaults),
+            }
+        }
+
+        let remaining = &args[index..];
+
+        if let Some(ref rest) = self.rest {
+            rest.bind(&LispCell::new_list(remaining.to_vec()), frame)?;
+        }
+
+        if self.keys.is_empty() {
+            return match self.rest.is_none() && !remaining.is_empty() {
+                true => Err(self.arity_error(name, args.len())),
+                false => Ok(defaults),
+            };
+        }
+
+        let mut passed_keys = vec![];
+        for pair in remaining.chunks(2) {
+            let key = match keyword_name(&pair[0]) {
+                Some(ref key) if self.keys.iter().any(|(param, _)| param == key) => key.clone(),
+                _ => {
+                    let expected = format!("keyword parameter of {}", self.signature(name));
+
+                    return Err(LispError::type_error(&expected, &pair[0]));
+                }
+            };
+
+            let value = match pair.get(1) {
+                Some(value) => value,
+                None => {
+                    let expected = format!("a value after :{} for {}", key, self.signature(name));
+
+                    return Err(LispError::arity(name, &expected, args.len()));
+                }
+            };
+
+            frame.def(key.clone(), value.clone());
+            passed_keys.push(key);
+        }
+
+        for (param, default) in self.keys.iter() {
+            if !passed_keys.contains(param) {
+                Self::bind_missing(&Pattern::symbol(param.clone()), default, frame, &mut defaults);
+            }
+        }
+
+        Ok(defaults)
+    }
+
+    fn bind_missing(
+        param: &Pattern,
+        default: &Option<LispCellRef>,
+        frame: &mut Environment,
+        defaults: &mut Vec<(Pattern, LispCellRef)>,
+    ) {
+        for name in param.names() {
+            frame.def(name, core::lisp_null());
+        }
+
+        if let Some(ref default) = *default {
+            defaults.push((param.clone(), default.clone()));
+        }
+    }
+
+    fn arity_error(&self, name: &str, found: usize) -> LispError {
+        let min = self.required.len();
+
+        let count = match (self.rest.is_some() || !self.keys.is_empty(), self.optional.len()) {
+            (true, _) => format!("at least {}", min),
+            (false, 0) => min.to_string(),
+            (false, n) => format!("{} to {}", min, min + n),
+        };
+
+        LispError::arity(name, &format!("{} for {}", count, self.signature(name)), found)
+    }
+
+    /// How a call to the function named `name` looks, such as `(foo a &optional b)`.
+    fn signature(&self, name: &str) -> String {
+        match print_cell(self.written.clone()).as_str() {
+            "()" => format!("({})", name),
+            written => format!("({} {}", name, &written[1..]),
+        }
+    }
+}
+
+fn keyword_name(cell: &LispCellRef) -> Option<String> {
+    match *cell.borrow() {
+        LispCell::Atom(ref name) if name.len() > 1 && name.starts_with(':') => Some(name[1..].to_string()),
+        _ => None,
+    }
+}
+
+fn to_param_name(cell: &LispCellRef) -> Result<String, LispError> {
+    match *cell.borrow() {
+        LispCell::Atom(ref name) => Ok(name.clone()),
+        _ => Err(LispError::type_error("symbol as keyword parameter", cell)),
+    }
+}
+
+fn to_defaulted_param(cell: &LispCellRef) -> Result<(LispCellRef, Option<LispCellRef>), LispError> {
+    if let LispCell::List(ref parts) = *cell.borrow() {
+        return match LispList::to_vec(parts.clone()).as_slice() {
+            [target, default] => Ok((target.clone(), Some(default.clone()))),
+            _ => Err(LispError::syntax("parameters with defaults must be (name default) pairs").with_form(cell)),
+        };
+    }
+
+    Ok((cell.clone(), None))
+}
+
+fn malformed(params: &LispCellRef) -> LispError {
+    LispError::syntax("parameters must be ordered as: required &optional &rest name &key").with_form(params)
+}
@@ -1,155 +1,876 @@
 use core::*;
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::io::BufRead;
+use std::mem;
 
-#[derive(Clone, Copy, Debug)]
-enum ParseMode {
-    Normal,
-    InStr,
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+    /// Record comments in `LispProgram::comments` instead of discarding them.
+    pub keep_comments: bool,
+}
+
+pub fn parse(program: String) -> Result<LispProgram, LispError> {
+    parse_with_options(program, ParseOptions::default())
 }
 
-pub fn parse(program: String) -> LispProgram {
-    let mut trimmed_program = program.trim().to_string();
-    log(|| println!("program: {}", &trimmed_program));
+pub fn parse_with_options(program: String, options: ParseOptions) -> Result<LispProgram, LispError> {
+    log(|| println!("program: {}", &program));
 
-    let entry = parse_init(&mut trimmed_program);
+    // Spans are offsets into the untouched program text so they line up with the caller's source
+    let (forms, spans, comments) = {
+        let mut tokenizer = Tokenizer::new(&program, options);
+        let mut parser = Parser::new();
 
-    LispProgram {
-        text: trimmed_program,
-        entry: Some(entry),
-    }
+        let mut forms = vec![];
+        while let Some(form) = parser.next_form(&mut tokenizer)? {
+            forms.push(form);
+        }
+
+        log(|| println!("forms: {:?}", &forms));
+
+        (forms, parser.spans, tokenizer.comments)
+    };
+
+    Ok(LispProgram {
+        text: program,
+        forms,
+        spans,
+        comments,
+    })
 }
 
-fn parse_init(program: &mut String) -> LispCellRef {
-    let mut sanitized_program = program.replace("(", " ( ").replace(")", " ) ");
-    log(|| println!("sanitized_program: {:?}", &sanitized_program));
+#[derive(Clone, Copy, Debug)]
+struct Mark {
+    pos: usize,
+    line: usize,
+    col: usize,
+}
 
-    let mut results = vec![];
-    parse_rec(&mut sanitized_program, true, ParseMode::Normal, &mut vec![], &mut String::new(), &mut results, 0);
+/// A string or block comment that ran into the end of incomplete input, kept so that scanning can
+/// carry on from where it stopped once more input arrives instead of starting the token over.
+#[derive(Debug)]
+enum Unfinished {
+    Str {
+        open: Mark,
+        text: String,
+    },
+    BlockComment {
+        open: Mark,
+        nesting: usize,
+    },
+}
 
-    log(|| println!("results: {:?}", &results));
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QuoteKind {
+    Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+}
 
-    return results.pop().unwrap();
+#[derive(Debug, PartialEq)]
+enum TokenKind<'a> {
+    Open,
+    Close,
+    Quote(QuoteKind),
+    DatumComment,
+    Str(String),
+    Word(&'a str),
 }
 
-fn parse_rec(
-    text: &mut String,
-    greedy: bool,
-    mode: ParseMode,
-    list_stack: &mut Vec<char>,
-    pending_word: &mut String,
-    results: &mut Vec<LispCellRef>,
-    depth: i32,
-) {
-    log(|| println!("{}results: {:?}", tab_to_depth(depth), &results));
+#[derive(Debug)]
+struct Token<'a> {
+    kind: TokenKind<'a>,
+    span: Span,
+}
+
+/// Splits program text into tokens, skipping (and optionally recording) whitespace and comments.
+///
+/// Works directly on byte offsets into the text so scanning is linear in its length.
+struct Tokenizer<'a> {
+    text: &'a str,
+    /// Whether `text` is all the input there is. When it isn't, a token that runs into the end of
+    /// `text` might continue in input that hasn't arrived yet, so the tokenizer stops in front of it.
+    complete: bool,
+    options: ParseOptions,
+    mark: Mark,
+    unfinished: Option<Unfinished>,
+    comments: Vec<LispComment>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(text: &'a str, options: ParseOptions) -> Tokenizer<'a> {
+        let start = Mark {
+            pos: 0,
+            line: 1,
+            col: 1,
+        };
+
+        Tokenizer::resume(text, true, options, start, None)
+    }
 
-    if text.is_empty() {
-        if pending_word != "" {
-            parse_rec_finalize_word(text, false, mode, list_stack, pending_word, results, depth);
+    fn resume(
+        text: &'a str,
+        complete: bool,
+        options: ParseOptions,
+        mark: Mark,
+        unfinished: Option<Unfinished>,
+    ) -> Tokenizer<'a> {
+        Tokenizer {
+            text,
+            complete,
+            options,
+            mark,
+            unfinished,
+            comments: vec![],
+        }
+    }
+
+    /// Returns the next token, or `None` once the text runs out (or, for incomplete input, once the
+    /// only thing left might be the start of a token that needs more input to finish).
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, LispError> {
+        match self.unfinished.take() {
+            Some(Unfinished::Str {
+                open,
+                text,
+            }) => return self.str_token(open, text),
+            Some(Unfinished::BlockComment {
+                open,
+                nesting,
+            }) => {
+                let is_closed = self.block_comment(open, nesting)?;
+
+                if !is_closed {
+                    return Ok(None);
+                }
+            }
+            None => {}
         }
 
-        return;
+        if !self.skip_trivia()? {
+            return Ok(None);
+        }
+
+        let start = self.mark;
+        let kind = match (self.peek(), self.peek_second()) {
+            (None, _) => return Ok(None),
+            (Some('('), _) => {
+                self.bump();
+                TokenKind::Open
+            }
+            (Some(')'), _) => {
+                self.bump();
+                TokenKind::Close
+            }
+            (Some('\''), _) => {
+                self.bump();
+                TokenKind::Quote(QuoteKind::Quote)
+            }
+            (Some('`'), _) => {
+                self.bump();
+                TokenKind::Quote(QuoteKind::Quasiquote)
+            }
+            (Some(','), Some('@')) => {
+                self.bump();
+                self.bump();
+                TokenKind::Quote(QuoteKind::UnquoteSplicing)
+            }
+            (Some(','), None) if !self.complete => return Ok(None),
+            (Some(','), _) => {
+                self.bump();
+                TokenKind::Quote(QuoteKind::Unquote)
+            }
+            (Some('#'), Some(';')) => {
+                self.bump();
+                self.bump();
+                TokenKind::DatumComment
+            }
+            (Some('"'), _) => {
+                self.bump();
+
+                return self.str_token(start, String::new());
+            }
+            (Some(_), _) => {
+                let word = self.parse_word();
+
+                if !self.complete && self.at_end() {
+                    self.mark = start;
+                    return Ok(None);
+                }
+
+                TokenKind::Word(word)
+            }
+        };
+
+        Ok(Some(Token {
+            kind,
+            span: self.span_from(start),
+        }))
     }
 
-    match text.remove(0) {
-        ' ' | '\n' => {
-            log(|| println!("{}in whitespace", tab_to_depth(depth)));
+    /// Skips over whitespace and line/block comments up to the start of the next token, returning
+    /// whether it got there (as opposed to stopping in front of a comment that needs more input).
+    fn skip_trivia(&mut self) -> Result<bool, LispError> {
+        loop {
+            let start = self.mark;
+
+            let kind = match (self.peek(), self.peek_second()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                    continue;
+                }
+                (Some(';'), _) => {
+                    self.skip_line_comment();
+
+                    if !self.complete && self.at_end() {
+                        self.mark = start;
+                        return Ok(false);
+                    }
 
-            parse_rec_finalize_word(text, greedy, mode, list_stack, pending_word, results, depth);
+                    LispCommentKind::Line
+                }
+                (Some('#'), Some('|')) => {
+                    self.bump();
+                    self.bump();
+
+                    if !self.block_comment(start, 1)? {
+                        return Ok(false);
+                    }
+
+                    continue;
+                }
+                _ => return Ok(true),
+            };
+
+            self.push_comment(kind, start, self.mark.pos);
         }
-        '\'' => {
-            log(|| println!("{}in '", tab_to_depth(depth)));
+    }
 
-            let mut to_quote = vec![];
-            parse_rec(text, false, mode, list_stack, &mut String::new(), &mut to_quote, depth);
+    fn push_comment(&mut self, kind: LispCommentKind, start: Mark, end: usize) {
+        log(|| println!("skipped comment: {:?}", &self.text[start.pos..end]));
+
+        if self.options.keep_comments {
+            self.comments.push(LispComment {
+                kind,
+                text: self.text[start.pos..end].to_string(),
+                span: Span {
+                    start: start.pos,
+                    end,
+                    line: start.line,
+                    col: start.col,
+                },
+            });
+        }
+    }
+
+    fn skip_line_comment(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' || c == '\r' {
+                break;
+            }
+
+            self.bump();
+        }
+    }
+
+    /// Skips the rest of a block comment `nesting` levels deep, recording it once it's closed. Returns
+    /// whether it got to the end of the comment, as opposed to saving its place to carry on from later.
+    fn block_comment(&mut self, open: Mark, nesting: usize) -> Result<bool, LispError> {
+        let mut nesting = nesting;
+
+        while nesting > 0 {
+            // Stopping short of the last character means a `|#` or `#|` split between two pieces of
+            // input still gets seen as a whole
+            if !self.complete && self.peek_second().is_none() {
+                self.unfinished = Some(Unfinished::BlockComment {
+                    open,
+                    nesting,
+                });
+
+                return Ok(false);
+            }
+
+            match (self.bump(), self.peek()) {
+                (None, _) => {
+                    return Err(LispError::parse("Invalid program: unterminated block comment", self.span_from(open)))
+                }
+                (Some('|'), Some('#')) => {
+                    self.bump();
+                    nesting -= 1;
+                }
+                (Some('#'), Some('|')) => {
+                    self.bump();
+                    nesting += 1;
+                }
+                _ => {}
+            }
+        }
 
-            log(|| println!("{}to quote: {:?}", tab_to_depth(depth), &to_quote));
+        self.push_comment(LispCommentKind::Block, open, self.mark.pos);
 
-            results.push(Rc::new(RefCell::new(LispCell::Quoted(to_quote.pop().unwrap()))));
+        Ok(true)
+    }
 
-            if greedy {
-                parse_rec(text, greedy, mode, list_stack, &mut String::new(), results, depth);
+    /// Reads the rest of a string literal that opened at `open`, with `text` holding what's been read
+    /// of it so far.
+    fn str_token(&mut self, open: Mark, text: String) -> Result<Option<Token<'a>>, LispError> {
+        let mut text = text;
+
+        loop {
+            let before = self.mark;
+
+            match self.bump() {
+                None if !self.complete => break,
+                None => return Err(LispError::parse("Invalid program: unterminated string", self.span_from(open))),
+                Some('"') => {
+                    return Ok(Some(Token {
+                        kind: TokenKind::Str(text),
+                        span: self.span_from(open),
+                    }))
+                }
+                Some('\\') => match self.parse_escape() {
+                    Ok(c) => text.push(c),
+                    // An escape sequence cut off by the end of the input might be finished by the next piece of it
+                    Err(_) if !self.complete && self.at_end() => {
+                        self.mark = before;
+                        break;
+                    }
+                    Err(err) => return Err(err),
+                },
+                Some(c) => text.push(c),
             }
         }
-        '(' => {
-            log(|| println!("{}in (", tab_to_depth(depth)));
 
-            list_stack.push('(');
+        self.unfinished = Some(Unfinished::Str {
+            open,
+            text,
+        });
+
+        Ok(None)
+    }
+
+    /// Parses the remainder of an escape sequence in a string literal (the `\` has already been consumed).
+    fn parse_escape(&mut self) -> Result<char, LispError> {
+        let start = Mark {
+            pos: self.mark.pos - 1,
+            line: self.mark.line,
+            col: self.mark.col - 1,
+        };
+
+        match self.bump() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('0') => Ok('\0'),
+            Some('u') => {
+                if self.bump() != Some('{') {
+                    return Err(LispError::parse("Invalid program: expected { in unicode escape", self.span_from(start)));
+                }
+
+                let digits_start = self.mark.pos;
+                while let Some(c) = self.peek() {
+                    if !c.is_ascii_hexdigit() {
+                        break;
+                    }
+
+                    self.bump();
+                }
+
+                let digits = &self.text[digits_start..self.mark.pos];
 
-            log(|| println!("{}Staring new results stack", tab_to_depth(depth)));
+                if self.bump() != Some('}') || digits.is_empty() || digits.len() > 6 {
+                    return Err(LispError::parse("Invalid program: malformed unicode escape", self.span_from(start)));
+                }
 
-            let mut list_contents = vec![];
-            parse_rec(text, true, mode, list_stack, &mut String::new(), &mut list_contents, depth + 1);
+                match u32::from_str_radix(digits, 16).ok().and_then(::std::char::from_u32) {
+                    Some(c) => Ok(c),
+                    None => Err(LispError::parse("Invalid program: invalid unicode code point", self.span_from(start))),
+                }
+            }
+            None => Err(LispError::parse("Invalid program: unterminated string", self.span_from(start))),
+            Some(_) => Err(LispError::parse("Invalid program: unknown escape sequence", self.span_from(start))),
+        }
+    }
 
-            log(|| println!("{}Finished results stack: {:?}", tab_to_depth(depth), &list_contents));
+    fn parse_word(&mut self) -> &'a str {
+        let start = self.mark.pos;
 
-            results.push(LispCell::new_list(list_contents));
+        while let Some(c) = self.peek() {
+            let ends_word = match (c, self.peek_second()) {
+                ('(', _) | (')', _) | ('"', _) | (';', _) | ('#', Some('|')) | ('#', Some(';')) => true,
+                (c, _) => c.is_whitespace(),
+            };
 
-            if greedy {
-                parse_rec(text, greedy, mode, list_stack, &mut String::new(), results, depth);
+            if ends_word {
+                break;
             }
+
+            self.bump();
+        }
+
+        &self.text[start..self.mark.pos]
+    }
+
+    fn at_end(&self) -> bool {
+        self.mark.pos >= self.text.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.mark.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.text[self.mark.pos..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+
+        self.mark.pos += c.len_utf8();
+
+        if c == '\n' {
+            self.mark.line += 1;
+            self.mark.col = 1;
+        } else {
+            self.mark.col += 1;
+        }
+
+        Some(c)
+    }
+
+    fn span_from(&self, start: Mark) -> Span {
+        Span {
+            start: start.pos,
+            end: self.mark.pos,
+            line: start.line,
+            col: start.col,
+        }
+    }
+}
+
+/// A form that is still waiting on more tokens before it's complete.
+enum Frame {
+    List {
+        open: Span,
+        list_contents: Vec<LispCellRef>,
+    },
+    Quote {
+        open: Span,
+        kind: QuoteKind,
+    },
+    DatumComment {
+        open: Span,
+        n_comments: usize,
+    },
+}
+
+/// Builds cells out of tokens, keeping unfinished forms on an explicit stack instead of the Rust
+/// call stack so nesting depth is only limited by memory.
+///
+/// The parser holds no reference to the text, so its state can be carried over while waiting on
+/// more input.
+struct Parser {
+    stack: Vec<Frame>,
+    /// How many of the frames on `stack` are datum comments.
+    datum_comments: usize,
+    spans: SourceMap,
+}
+
+impl Parser {
+    fn new() -> Parser {
+        Parser {
+            stack: vec![],
+            datum_comments: 0,
+            spans: SourceMap::new(),
         }
-        ')' => {
-            log(|| println!("{}in )", tab_to_depth(depth)));
+    }
+
+    /// Parses the next top-level form, or returns `None` if the tokenizer runs out of tokens first.
+    fn next_form(&mut self, tokenizer: &mut Tokenizer) -> Result<Option<LispCellRef>, LispError> {
+        while let Some(token) = tokenizer.next_token()? {
+            log(|| println!("{}token: {:?}", tab_to_depth(self.stack.len()), &token));
+
+            let finished = match token.kind {
+                TokenKind::Open => {
+                    self.stack.push(Frame::List {
+                        open: token.span,
+                        list_contents: vec![],
+                    });
 
-            if list_stack.is_empty() {
-                // TODO: handle this better
-                panic!("Invalid program: unmatched parens");
+                    None
+                }
+                TokenKind::Quote(kind) => {
+                    self.stack.push(Frame::Quote {
+                        open: token.span,
+                        kind,
+                    });
+
+                    None
+                }
+                TokenKind::DatumComment => {
+                    self.datum_comments += 1;
+                    self.stack.push(Frame::DatumComment {
+                        open: token.span,
+                        n_comments: tokenizer.comments.len(),
+                    });
+
+                    None
+                }
+                TokenKind::Close => match self.stack.pop() {
+                    Some(Frame::List {
+                        open,
+                        list_contents,
+                    }) => {
+                        let span = join_spans(open, token.span);
+
+                        self.finish_form(LispCell::new_list(list_contents), span, tokenizer)
+                    }
+                    Some(frame) => return Err(unfinished_form_error(&frame)),
+                    None => return Err(LispError::parse("Invalid program: unmatched parens", token.span)),
+                },
+                TokenKind::Str(string) => self.finish_form(LispCell::Str(string).to_ref(), token.span, tokenizer),
+                TokenKind::Word(word) => self.finish_form(make_word(word, token.span)?, token.span, tokenizer),
+            };
+
+            if finished.is_some() {
+                return Ok(finished);
             }
+        }
 
-            list_stack.pop();
+        if tokenizer.complete {
+            if let Some(frame) = self.stack.last() {
+                return Err(unfinished_form_error(frame));
+            }
         }
-        '"' => match mode {
-            ParseMode::InStr => {
-                results.push(LispCell::Str(pending_word.clone()).to_ref());
 
-                if greedy {
-                    parse_rec(text, greedy, ParseMode::Normal, list_stack, &mut String::from(""), results, depth)
+        Ok(None)
+    }
+
+    fn is_mid_form(&self) -> bool {
+        !self.stack.is_empty()
+    }
+
+    /// Hands a completed cell to whatever form encloses it, finishing any quotes or datum comments
+    /// that were waiting on it along the way. Returns the cell back if it's a finished top-level form.
+    fn finish_form(&mut self, cell: LispCellRef, span: Span, tokenizer: &mut Tokenizer) -> Option<LispCellRef> {
+        let mut cell = cell;
+        let mut span = span;
+
+        loop {
+            // Commented out cells get dropped, and their addresses could be reused by later cells
+            if self.datum_comments == 0 {
+                self.spans.insert(&cell, span);
+            }
+
+            match self.stack.pop() {
+                Some(Frame::Quote {
+                    open,
+                    kind,
+                }) => {
+                    let quoted = match kind {
+                        QuoteKind::Quote => LispCell::Quoted(cell),
+                        QuoteKind::Quasiquote => LispCell::Quasiquoted(cell),
+                        QuoteKind::Unquote => LispCell::Unquoted(cell),
+                        QuoteKind::UnquoteSplicing => LispCell::UnquoteSpliced(cell),
+                    };
+
+                    cell = quoted.to_ref();
+                    span = join_spans(open, span);
+                }
+                Some(Frame::DatumComment {
+                    open,
+                    n_comments,
+                }) => {
+                    self.datum_comments -= 1;
+
+                    // Anything inside the commented out form is part of this comment's text already
+                    tokenizer.comments.truncate(n_comments);
+
+                    let start = Mark {
+                        pos: open.start,
+                        line: open.line,
+                        col: open.col,
+                    };
+                    tokenizer.push_comment(LispCommentKind::Datum, start, span.end);
+
+                    return None;
+                }
+                Some(Frame::List {
+                    open,
+                    mut list_contents,
+                }) => {
+                    list_contents.push(cell);
+                    self.stack.push(Frame::List {
+                        open,
+                        list_contents,
+                    });
+
+                    return None;
                 }
+                None => return Some(cell),
             }
-            _ => parse_rec(text, greedy, ParseMode::InStr, list_stack, pending_word, results, depth),
-        },
-        c @ _ => {
-            log(|| println!("{}c: {}", tab_to_depth(depth), &c));
+        }
+    }
+}
+
+/// Reads forms out of text that arrives a piece at a time, like lines typed into a REPL or a file
+/// that's too big to load at once.
+///
+/// Text goes in through `feed`, and each call to `next_form` hands back the next complete top-level
+/// form as its own `LispProgram` or `None` if more input is needed first. Once there's no more input,
+/// `finish` makes the reader treat whatever's left as all there is, so a trailing partial form
+/// becomes an error instead of waiting forever.
+///
+/// Unfinished forms, strings and block comments are carried over between calls rather than re-read.
+/// The only things read again are a word or line comment that a piece of input ends partway through,
+/// so reading is linear in the size of the input as long as it's fed in whole lines.
+pub struct Reader {
+    buffer: String,
+    mark: Mark,
+    unfinished: Option<Unfinished>,
+    complete: bool,
+    parser: Parser,
+    failed_text: String,
+}
+
+impl Reader {
+    pub fn new() -> Reader {
+        Reader {
+            buffer: String::new(),
+            mark: Mark {
+                pos: 0,
+                line: 1,
+                col: 1,
+            },
+            unfinished: None,
+            complete: false,
+            parser: Parser::new(),
+            failed_text: String::new(),
+        }
+    }
+
+    pub fn feed(&mut self, text: &str) {
+        self.buffer.push_str(text);
+    }
+
+    /// Marks the end of the input.
+    pub fn finish(&mut self) {
+        self.complete = true;
+    }
+
+    /// Whether the reader is partway through a form that needs more input to finish.
+    pub fn is_mid_form(&self) -> bool {
+        self.parser.is_mid_form() || self.unfinished.is_some() || !self.buffer[self.mark.pos..].trim().is_empty()
+    }
+
+    /// Whether all input has been fed in and read.
+    pub fn is_finished(&self) -> bool {
+        self.complete && !self.is_mid_form()
+    }
+
+    /// Reads the next complete top-level form, if there's enough input for one.
+    ///
+    /// The returned program's text starts at the beginning of the line the form starts on, and its
+    /// spans point into that text but keep their line numbers from the input as a whole.
+    ///
+    /// On a syntax error everything the reader had buffered is thrown away so that it can pick back
+    /// up with whatever input comes next; `render_error` can still show where the error was.
+    pub fn next_form(&mut self) -> Result<Option<LispProgram>, LispError> {
+        // A saved string or block comment still points at where it opened in the buffer
+        if !self.parser.is_mid_form() && self.unfinished.is_none() {
+            self.compact();
+        }
+
+        let result = {
+            let unfinished = self.unfinished.take();
+            let mut tokenizer =
+                Tokenizer::resume(&self.buffer, self.complete, ParseOptions::default(), self.mark, unfinished);
+            let result = self.parser.next_form(&mut tokenizer);
+            self.mark = tokenizer.mark;
+            self.unfinished = tokenizer.unfinished.take();
+
+            result
+        };
+
+        match result {
+            Ok(Some(form)) => Ok(Some(self.take_program(form))),
+            Ok(None) => Ok(None),
+            Err(err) => Err(self.discard(err)),
+        }
+    }
+
+    /// Renders the last error returned by `next_form` against the input that caused it.
+    pub fn render_error(&self, err: &LispError) -> String {
+        err.render(&self.failed_text)
+    }
+
+    fn take_program(&mut self, form: LispCellRef) -> LispProgram {
+        let span = self.parser.spans.span_of(&form).expect("top-level forms should always have a span");
+        let line_start = line_start_before(&self.buffer, span.start);
 
-            // We're either starting or adding to a pending word
-            pending_word.push(c);
+        let mut spans = mem::replace(&mut self.parser.spans, SourceMap::new());
+        spans.rebase(line_start);
 
-            // ...either way, we just continue
-            parse_rec(text, greedy, mode, list_stack, pending_word, results, depth)
+        LispProgram {
+            text: self.buffer[line_start..span.end].to_string(),
+            forms: vec![form],
+            spans,
+            comments: vec![],
+        }
+    }
+
+    fn discard(&mut self, err: LispError) -> LispError {
+        let mut err = err;
+
+        if let Some(ref mut span) = err.span {
+            let line_start = line_start_before(&self.buffer, span.start);
+
+            self.failed_text = self.buffer[line_start..].to_string();
+            span.start -= line_start;
+            span.end -= line_start;
+        }
+
+        self.mark = advance_mark(self.mark, &self.buffer[self.mark.pos..]);
+        self.mark.pos = 0;
+        self.buffer.clear();
+        self.unfinished = None;
+        self.parser = Parser::new();
+
+        err
+    }
+
+    /// Drops text that's already been read, keeping the line the next form starts on.
+    fn compact(&mut self) {
+        let consumed = line_start_before(&self.buffer, self.mark.pos);
+
+        // Only compacting once at least half the buffer is stale keeps this amortized linear
+        if consumed > 0 && consumed >= self.buffer.len() / 2 {
+            self.buffer.drain(..consumed);
+            self.mark.pos -= consumed;
+        }
+    }
+}
+
+impl Default for Reader {
+    fn default() -> Reader {
+        Reader::new()
+    }
+}
+
+/// Lazily reads forms line by line out of `input`, e.g. a file or stdin.
+pub fn read_forms<R: BufRead>(input: R) -> Forms<R> {
+    Forms {
+        input,
+        reader: Reader::new(),
+        failed: false,
+    }
+}
+
+pub struct Forms<R> {
+    input: R,
+    reader: Reader,
+    failed: bool,
+}
+
+impl<R> Forms<R> {
+    pub fn reader(&self) -> &Reader {
+        &self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for Forms<R> {
+    type Item = Result<LispProgram, LispError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.failed {
+                return None;
+            }
+
+            match self.reader.next_form() {
+                Ok(Some(program)) => return Some(Ok(program)),
+                Ok(None) => {}
+                Err(err) => return Some(Err(err)),
+            }
+
+            if self.reader.is_finished() {
+                return None;
+            }
+
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) => self.reader.finish(),
+                Ok(_) => self.reader.feed(&line),
+                Err(err) => {
+                    self.failed = true;
+
+                    return Some(Err(LispError::new(LispErrorKind::Io(err.to_string()))));
+                }
+            }
         }
     }
 }
 
-fn parse_rec_finalize_word(
-    text: &mut String,
-    greedy: bool,
-    mode: ParseMode,
-    list_stack: &mut Vec<char>,
-    pending_word: &mut String,
-    results: &mut Vec<LispCellRef>,
-    depth: i32,
-) {
-    // If there's no pending_word, just move onto the next char
-    if pending_word == "" {
-        return parse_rec(text, greedy, mode, list_stack, pending_word, results, depth);
+fn line_start_before(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0)
+}
+
+fn advance_mark(mark: Mark, text: &str) -> Mark {
+    let mut mark = mark;
+
+    for c in text.chars() {
+        mark.pos += c.len_utf8();
+
+        if c == '\n' {
+            mark.line += 1;
+            mark.col = 1;
+        } else {
+            mark.col += 1;
+        }
     }
 
-    log(|| println!("{}finalizing word: {}", tab_to_depth(depth), &pending_word));
+    mark
+}
+
+fn make_word(word: &str, span: Span) -> Result<LispCellRef, LispError> {
+    match word {
+        "#t" | "true" => return Ok(LispCell::Bool(true).to_ref()),
+        "#f" | "false" => return Ok(LispCell::Bool(false).to_ref()),
+        "nil" => return Ok(LispCell::List(LispList::new().to_ref()).to_ref()),
+        _ => {}
+    }
 
-    let pending_word_str = pending_word.to_string();
-    let cell = match pending_word_str.parse::<f32>() {
-        Ok(num) => LispCell::Number(num),
-        _ => LispCell::Atom(pending_word_str),
+    let cell = match LispNumber::parse(word) {
+        Ok(Some(num)) => LispCell::Number(num),
+        Ok(None) => LispCell::Atom(word.to_string()),
+        Err(msg) => return Err(LispError::parse(msg, span)),
     };
 
-    // Otherwise, close out this word and add it to the result set
-    results.push(Rc::new(RefCell::new(cell)));
+    Ok(cell.to_ref())
+}
+
+fn unfinished_form_error(frame: &Frame) -> LispError {
+    match *frame {
+        Frame::List {
+            open, ..
+        } => LispError::parse("Invalid program: unclosed parens", open),
+        Frame::Quote {
+            open, ..
+        } => LispError::parse("Invalid program: nothing to quote", open),
+        Frame::DatumComment {
+            open, ..
+        } => LispError::parse("Invalid program: nothing to comment out after #;", open),
+    }
+}
 
-    if greedy {
-        // Move onto the next char
-        parse_rec(text, greedy, mode, list_stack, &mut String::new(), results, depth);
+fn join_spans(start: Span, end: Span) -> Span {
+    Span {
+        start: start.start,
+        end: end.end,
+        line: start.line,
+        col: start.col,
     }
 }
 
@@ -162,6 +883,6 @@ where
     }
 }
 
-fn tab_to_depth(depth: i32) -> String {
-    format!("({}): {}", depth, "  ".repeat(depth as usize).to_string())
+fn tab_to_depth(depth: usize) -> String {
+    format!("({}): {}", depth, "  ".repeat(depth))
 }
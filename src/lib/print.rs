@@ -1,13 +1,34 @@
 use core::*;
 
-use std::cell::RefCell;
-use std::rc::Rc;
-
 pub fn print(program: &LispProgram) -> String {
-    match program.entry {
-        None => "".to_string(),
-        Some(ref entry) => print_cell(entry.clone()),
+    let mut lines = vec![];
+
+    // Comments that sit between top-level forms are printed back in place; ones nested inside a
+    // form stay in `program.comments` but aren't re-emitted
+    let mut comments = program.comments.iter().peekable();
+    let mut last_end = 0;
+
+    for form in program.forms.iter() {
+        if let Some(span) = program.span_of(form) {
+            while let Some(comment) = comments.next_if(|comment| comment.span.start < span.start) {
+                if comment.span.start >= last_end {
+                    lines.push(comment.text.clone());
+                }
+            }
+
+            last_end = span.end;
+        }
+
+        lines.push(print_cell(form.clone()));
     }
+
+    for comment in comments {
+        if comment.span.start >= last_end {
+            lines.push(comment.text.clone());
+        }
+    }
+
+    lines.join("\n")
 }
 
 pub fn print_cell(cell: LispCellRef) -> String {
@@ -17,38 +38,85 @@ pub fn print_cell(cell: LispCellRef) -> String {
     result
 }
 
-fn print_rec(node: LispCellRef, result: &mut String) {
-    match *node.borrow() {
-        LispCell::Func(ref func) => {
-            result.push_str(format!("#{}", &func.name).as_str())
-        }
-        LispCell::Quoted(ref quoted) => {
-            let mut quoted_result = String::new();
-            print_rec(quoted.clone(), &mut quoted_result);
+fn print_str(string: &str, result: &mut String) {
+    result.push('"');
 
-            result.push_str(format!("'{}", quoted_result).as_str());
+    for c in string.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\0' => result.push_str("\\0"),
+            c if c.is_control() => result.push_str(format!("\\u{{{:x}}}", c as u32).as_str()),
+            c => result.push(c),
         }
-        LispCell::Number(num) => result.push_str(num.to_string().as_str()),
-        LispCell::Atom(ref atom) => result.push_str(atom.as_str()),
-        LispCell::Str(ref string) => result.push_str(format!("\"{}\"", string).as_str()),
-        LispCell::List(ref list) => {
-            result.push('(');
+    }
 
-            let list_vec = LispList::to_vec(list.clone());
+    result.push('"');
+}
 
-            let n = list_vec.len();
-            for i in 0..n {
-                let cell = &list_vec[i];
+/// What's left for `print_rec` to print: a cell, or punctuation to put after the cells before it.
+enum Pending {
+    Cell(LispCellRef),
+    Text(&'static str),
+}
 
-                print_rec(cell.clone(), result);
+// Nested lists and quotes are printed from an explicit stack rather than by recursing, so deeply
+// nested values can't overflow the Rust stack
+fn print_rec(node: LispCellRef, result: &mut String) {
+    let mut pending = vec![Pending::Cell(node)];
 
-                if i != n - 1 {
-                    result.push(' ');
-                }
+    while let Some(next) = pending.pop() {
+        let node = match next {
+            Pending::Cell(node) => node,
+            Pending::Text(text) => {
+                result.push_str(text);
+                continue;
+            }
+        };
+
+        let cell = node.borrow();
+
+        match *cell {
+            LispCell::Func(ref func) => {
+                result.push_str(format!("#{}", &func.name).as_str())
             }
+            LispCell::Quoted(ref quoted) => {
+                result.push('\'');
+                pending.push(Pending::Cell(quoted.clone()));
+            }
+            LispCell::Quasiquoted(ref quoted) => {
+                result.push('`');
+                pending.push(Pending::Cell(quoted.clone()));
+            }
+            LispCell::Unquoted(ref unquoted) => {
+                result.push(',');
+                pending.push(Pending::Cell(unquoted.clone()));
+            }
+            LispCell::UnquoteSpliced(ref unquoted) => {
+                result.push_str(",@");
+                pending.push(Pending::Cell(unquoted.clone()));
+            }
+            LispCell::Number(ref num) => result.push_str(num.to_string().as_str()),
+            LispCell::Bool(val) => result.push_str(val.to_string().as_str()),
+            LispCell::Atom(ref atom) => result.push_str(atom.as_str()),
+            LispCell::Error(ref err) => result.push_str(format!("#<error {}>", err.kind).as_str()),
+            LispCell::Str(ref string) => print_str(string, result),
+            LispCell::List(ref list) => {
+                result.push('(');
+                pending.push(Pending::Text(")"));
 
-            result.push(')');
+                // Pushed last to first, so they're popped off in order
+                for (i, cell) in LispList::to_vec(list.clone()).into_iter().enumerate().rev() {
+                    pending.push(Pending::Cell(cell));
+
+                    if i != 0 {
+                        pending.push(Pending::Text(" "));
+                    }
+                }
+            }
         }
-        ref c @ _ => panic!("Unsupported LispCell type: {:?}", c),
     }
 }
@@ -1,24 +1,32 @@
-use std::cell::RefCell;
 use std::collections::linked_list::LinkedList;
 use std::rc::Rc;
 
-use super::{LispCell, LispCellRef};
+use super::{LispCell, LispCellRef, LispNumber};
 
 pub fn split_at_head<T>(list: &mut LinkedList<Rc<T>>) -> (Option<Rc<T>>, LinkedList<Rc<T>>) {
-    let head = match list.front() {
-        Some(head) => Some(head.clone()),
-        None => None,
-    };
+    let head = list.front().cloned();
 
     (head, list.split_off(1))
 }
 
-pub fn make_num(num: f32) -> LispCellRef {
-    Rc::new(RefCell::new(LispCell::Number(num)))
+pub fn make_num(num: LispNumber) -> LispCellRef {
+    LispCell::Number(num).to_ref()
+}
+
+pub fn make_int(num: i64) -> LispCellRef {
+    make_num(LispNumber::Int(num))
+}
+
+pub fn make_float(num: f64) -> LispCellRef {
+    make_num(LispNumber::Float(num))
 }
 
 pub fn make_atom(name: &'static str) -> LispCellRef {
-    Rc::new(RefCell::new(LispCell::Atom(name.to_string())))
+    LispCell::Atom(name.to_string()).to_ref()
+}
+
+pub fn make_str(string: &str) -> LispCellRef {
+    LispCell::Str(string.to_string()).to_ref()
 }
 
 pub fn make_list(list: Vec<LispCellRef>) -> LispCellRef {
@@ -26,9 +34,9 @@ pub fn make_list(list: Vec<LispCellRef>) -> LispCellRef {
 }
 
 pub fn make_quoted(cell: LispCellRef) -> LispCellRef {
-    Rc::new(RefCell::new(LispCell::Quoted(cell)))
+    LispCell::Quoted(cell).to_ref()
 }
 
 pub fn make_bool(val: bool) -> LispCellRef {
-    Rc::new(RefCell::new(LispCell::Bool(val)))
+    LispCell::Bool(val).to_ref()
 }